@@ -1,5 +1,12 @@
-use clap::{Parser, Subcommand};
+use clap::{ArgMatches, Parser, Subcommand};
 use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::report::OutputFormat;
+
+fn parse_timeout(s: &str) -> Result<Duration, String> {
+    crate::config::parse_duration(s).map_err(|e| e.to_string())
+}
 
 /// Terradrift – Terraform drift detector
 #[derive(Parser, Debug)]
@@ -28,6 +35,34 @@ pub enum Commands {
         /// Limit concurrency (defaults to logical CPU cores)
         #[arg(short = 'j', long)]
         jobs: Option<usize>,
+
+        /// Timeout per workspace for init and plan, e.g. 90s, 15m, 1h (overrides config)
+        #[arg(long, value_parser = parse_timeout)]
+        timeout: Option<Duration>,
+
+        /// Read each plan to completion for exact change counts (slower)
+        #[arg(long)]
+        full_scan: bool,
+
+        /// Also list each drifted resource (address, action, type, provider)
+        #[arg(long)]
+        details: bool,
+
+        /// Output format; repeat for several (default: table on a terminal, json otherwise)
+        #[arg(long = "format", value_enum, value_name = "FORMAT")]
+        formats: Vec<OutputFormat>,
+
+        /// Write the preceding --format to this file instead of stdout
+        #[arg(long = "output", value_name = "PATH")]
+        outputs: Vec<PathBuf>,
+
+        /// Also write a JUnit XML report (shorthand for `--format junit --output PATH`)
+        #[arg(long, value_name = "PATH")]
+        junit: Option<PathBuf>,
+
+        /// Where the HTML report will be published; linked from the Slack alert
+        #[arg(long, value_name = "URL")]
+        report_url: Option<String>,
     },
     /// Print build information
     Version {
@@ -36,3 +71,82 @@ pub enum Commands {
         json: bool,
     },
 }
+
+/// Pair each `--output` of the `diff` subcommand with the `--format` given
+/// before it. Formats without an output go to stdout.
+pub fn output_targets(diff: &ArgMatches) -> Result<Vec<(OutputFormat, Option<PathBuf>)>, String> {
+    let indexed = |id: &str| -> Vec<usize> {
+        diff.indices_of(id)
+            .map(|indices| indices.collect())
+            .unwrap_or_default()
+    };
+    let formats = indexed("formats").into_iter().zip(
+        diff.get_many::<OutputFormat>("formats")
+            .into_iter()
+            .flatten()
+            .copied(),
+    );
+    let outputs = indexed("outputs").into_iter().zip(
+        diff.get_many::<PathBuf>("outputs")
+            .into_iter()
+            .flatten()
+            .cloned(),
+    );
+    pair_outputs(formats.collect(), outputs.collect())
+}
+
+fn pair_outputs(
+    formats: Vec<(usize, OutputFormat)>,
+    outputs: Vec<(usize, PathBuf)>,
+) -> Result<Vec<(OutputFormat, Option<PathBuf>)>, String> {
+    let mut targets: Vec<(usize, OutputFormat, Option<PathBuf>)> = formats
+        .into_iter()
+        .map(|(index, format)| (index, format, None))
+        .collect();
+    for (index, path) in outputs {
+        let target = targets
+            .iter_mut()
+            .rev()
+            .find(|(i, _, _)| *i < index)
+            .ok_or_else(|| format!("--output {} must follow a --format", path.display()))?;
+        if target.2.is_some() {
+            return Err(format!(
+                "--output {} has no --format of its own",
+                path.display()
+            ));
+        }
+        target.2 = Some(path);
+    }
+    Ok(targets
+        .into_iter()
+        .map(|(_, format, path)| (format, path))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::{CommandFactory, FromArgMatches};
+
+    fn targets(args: &[&str]) -> Result<Vec<(OutputFormat, Option<PathBuf>)>, String> {
+        let matches = Cli::command()
+            .try_get_matches_from(["terradrift", "diff", "-p", "prod"].iter().chain(args))
+            .unwrap();
+        Cli::from_arg_matches(&matches).unwrap();
+        output_targets(matches.subcommand_matches("diff").unwrap())
+    }
+
+    #[test]
+    fn pairs_outputs_with_preceding_format() {
+        assert_eq!(
+            targets(&["--format", "table", "--format", "json", "--output", "out.json"]).unwrap(),
+            [
+                (OutputFormat::Table, None),
+                (OutputFormat::Json, Some(PathBuf::from("out.json")))
+            ]
+        );
+        assert!(targets(&["--output", "out.json", "--format", "json"]).is_err());
+        assert!(targets(&["--format", "csv", "--output", "a.csv", "--output", "b.csv"]).is_err());
+        assert!(targets(&[]).unwrap().is_empty());
+    }
+}
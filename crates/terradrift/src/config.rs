@@ -1,10 +1,15 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
 use std::path::PathBuf;
+use std::time::Duration;
 
 use anyhow::{Context, Result};
 use serde::Deserialize;
 
+use crate::metrics::MetricsConfig;
+use crate::terraform::{Engine, PlanMode, ScanMode};
+use crate::toolchain::VersionPolicy;
+
 const DEFAULT_CONFIG_NAME: &str = "terradrift.toml";
 
 #[derive(Debug, Deserialize)]
@@ -17,6 +22,107 @@ pub struct Profile {
     pub storage: Storage,
     /// Optional workspace-specific concurrency override
     pub jobs: Option<usize>,
+    /// CLI used for plans: `terraform` (default) or `tofu`
+    #[serde(default)]
+    pub engine: Engine,
+    /// How each state's recorded `terraform_version` picks the binary:
+    /// `exact`, `same-minor` (default) or `at-least`
+    #[serde(default)]
+    pub version_policy: VersionPolicy,
+    /// Root of the Terraform configuration tree plans are run from.
+    /// When unset, plans run in the current directory.
+    pub root_dir: Option<PathBuf>,
+    /// Configuration directory of a workspace, relative to `root_dir`.
+    /// `{workspace}` is replaced by the workspace name (default: `root_dir` itself).
+    pub workspace_dir: Option<String>,
+    /// OpenPGP keyring used to verify downloaded release checksums instead of
+    /// the pinned HashiCorp key.
+    pub release_keyring: Option<PathBuf>,
+    /// Timeout per workspace for `init` and its plans together (e.g. `"90s"`,
+    /// `"15m"`, `"1h"`)
+    #[serde(default, deserialize_with = "deserialize_duration")]
+    pub timeout: Option<Duration>,
+    /// `fast` (default) stops each plan at the first change; `full` reads it to
+    /// completion for exact per-action counts
+    #[serde(default)]
+    pub scan_mode: ScanMode,
+    /// `normal` (default) plan, or `refresh-only` to report only changes made
+    /// outside Terraform as drift
+    #[serde(default)]
+    pub mode: PlanMode,
+    /// In `refresh-only` mode, follow up with a normal plan and report its
+    /// remaining changes as `pending_config_change`
+    #[serde(default)]
+    pub pending_changes: bool,
+    /// Prometheus textfile and/or Pushgateway export
+    #[serde(default)]
+    pub metrics: MetricsConfig,
+    /// Notification targets (`[[profiles.<name>.sinks]]`)
+    #[serde(default)]
+    pub sinks: Vec<SinkConfig>,
+    /// Per-workspace overrides, keyed by workspace name
+    #[serde(default)]
+    pub workspaces: HashMap<String, WorkspaceOverrides>,
+}
+
+/// Settings a single workspace may override. Anything else under
+/// `[profiles.<name>.workspaces.<workspace>]` is rejected rather than ignored.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WorkspaceOverrides {
+    /// Timeout for this workspace, overriding the profile's
+    #[serde(default, deserialize_with = "deserialize_duration")]
+    pub timeout: Option<Duration>,
+}
+
+impl Profile {
+    /// Resolve the Terraform configuration directory for `workspace`, if the
+    /// profile maps workspaces to directories.
+    pub fn workspace_config_dir(&self, workspace: &str) -> Option<PathBuf> {
+        let root = self.root_dir.as_ref()?;
+        match &self.workspace_dir {
+            Some(template) => Some(root.join(template.replace("{workspace}", workspace))),
+            None => Some(root.clone()),
+        }
+    }
+
+    /// Timeout for `workspace`: its own override, else the profile's.
+    pub fn workspace_timeout(&self, workspace: &str) -> Option<Duration> {
+        self.workspaces
+            .get(workspace)
+            .and_then(|w| w.timeout)
+            .or(self.timeout)
+    }
+}
+
+/// Parse a duration such as `"30"`/`"30s"`, `"15m"` or `"2h"`.
+pub fn parse_duration(s: &str) -> Result<Duration> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (num, unit) = s.split_at(split);
+    let n: u64 = num
+        .parse()
+        .with_context(|| format!("Invalid duration '{s}'"))?;
+    let scale = match unit.trim() {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        other => anyhow::bail!("Invalid duration unit '{other}' in '{s}' (use s, m or h)"),
+    };
+    let secs = n
+        .checked_mul(scale)
+        .with_context(|| format!("Duration '{s}' is too long"))?;
+    Ok(Duration::from_secs(secs))
+}
+
+fn deserialize_duration<'de, D>(deserializer: D) -> std::result::Result<Option<Duration>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let raw = Option::<String>::deserialize(deserializer)?;
+    raw.map(|s| parse_duration(&s))
+        .transpose()
+        .map_err(serde::de::Error::custom)
 }
 
 #[derive(Debug, Deserialize)]
@@ -42,6 +148,151 @@ pub enum Storage {
     },
 }
 
+/// A notification target and when to notify it.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SinkConfig {
+    #[serde(flatten)]
+    pub kind: SinkKind,
+    /// Which runs are sent (default: `always` for PagerDuty, which resolves
+    /// incidents on clean runs, else `drift`)
+    pub when: Option<NotifyWhen>,
+}
+
+impl SinkConfig {
+    pub fn when(&self) -> NotifyWhen {
+        self.when.unwrap_or(match self.kind {
+            SinkKind::Pagerduty { .. } => NotifyWhen::Always,
+            _ => NotifyWhen::Drift,
+        })
+    }
+
+    /// Reject settings that would silently break the sink.
+    fn validate(&self) -> Result<()> {
+        if matches!(self.kind, SinkKind::Pagerduty { .. }) && self.when() != NotifyWhen::Always {
+            // Clean runs are what resolve incidents.
+            anyhow::bail!(
+                "PagerDuty sinks must be notified of every run (when = \"always\") to resolve incidents"
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Sink type (`type = "..."`) and its settings. Secrets such as webhook URLs
+/// may be given as `env:NAME` references (see [`resolve_secret`]).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum SinkKind {
+    Slack {
+        /// Incoming webhook URL
+        url: String,
+        /// Link to each workspace's plan; `{profile}` and `{workspace}` are
+        /// substituted
+        plan_url: Option<String>,
+    },
+    /// Microsoft Teams incoming webhook (Adaptive Card)
+    Teams {
+        /// Incoming webhook or Workflows URL
+        url: String,
+        /// Link to each workspace's plan, as for Slack
+        plan_url: Option<String>,
+    },
+    /// POST of the JSON summary to any URL, optionally signed
+    Webhook {
+        url: String,
+        /// Extra request headers; values may be secret references
+        #[serde(default)]
+        headers: BTreeMap<String, String>,
+        /// Shared secret for the `X-Terradrift-Signature` HMAC-SHA256
+        secret: Option<String>,
+    },
+    /// PagerDuty Events API v2: one incident per drifting workspace, resolved
+    /// once it scans clean
+    Pagerduty {
+        /// Integration (routing) key of the service
+        routing_key: String,
+        /// Severity of a workspace's incident by the actions it would take
+        #[serde(default)]
+        severity: SeverityMap,
+        /// Events API endpoint (default: PagerDuty's US service region)
+        events_url: Option<String>,
+        /// Directory the workspaces with open incidents are kept in between
+        /// runs (default: `terradrift/pagerduty` in the user cache directory).
+        /// Scheduled scans on ephemeral runners must persist it, or clean
+        /// runs cannot resolve incidents.
+        state_dir: Option<PathBuf>,
+    },
+}
+
+/// PagerDuty event severity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+    Critical,
+}
+
+impl Severity {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+            Severity::Critical => "critical",
+        }
+    }
+}
+
+/// Severity per plan action; a workspace's incident takes the highest among
+/// its changed resources.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SeverityMap {
+    pub create: Severity,
+    pub update: Severity,
+    pub delete: Severity,
+    pub replace: Severity,
+    /// Reads, forgets, and drift without recorded resources
+    pub other: Severity,
+}
+
+impl Default for SeverityMap {
+    fn default() -> Self {
+        SeverityMap {
+            create: Severity::Warning,
+            update: Severity::Error,
+            delete: Severity::Critical,
+            replace: Severity::Critical,
+            other: Severity::Warning,
+        }
+    }
+}
+
+/// Runs a sink is notified of.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NotifyWhen {
+    /// Some workspace drifted
+    #[default]
+    Drift,
+    /// Some workspace failed or timed out
+    Error,
+    /// Every run
+    Always,
+}
+
+/// Resolve a secret setting: `env:NAME` reads environment variable `NAME`,
+/// anything else is used as-is.
+pub fn resolve_secret(value: &str) -> Result<String> {
+    match value.strip_prefix("env:") {
+        Some(name) => std::env::var(name)
+            .with_context(|| format!("Secret environment variable {name} is not set")),
+        None => Ok(value.to_string()),
+    }
+}
+
 impl Config {
     /// Load configuration from an explicit path, or search upward from current dir.
     pub fn load(path_override: Option<PathBuf>) -> Result<Self> {
@@ -55,6 +306,12 @@ impl Config {
             .with_context(|| format!("Reading config file {}", path.display()))?;
         let cfg: Config = toml::from_str(&contents)
             .with_context(|| format!("Parsing TOML config {}", path.display()))?;
+        for (name, profile) in &cfg.profiles {
+            for sink in &profile.sinks {
+                sink.validate()
+                    .with_context(|| format!("Invalid sink in profile '{name}'"))?;
+            }
+        }
         Ok(cfg)
     }
 
@@ -123,4 +380,124 @@ path = "/tmp"
         let result = cfg.profile("does_not_exist");
         assert!(result.is_err());
     }
+
+    #[test]
+    fn workspace_dir_template() {
+        let toml = r#"[profiles.prod]
+root_dir = "/srv/infra"
+workspace_dir = "envs/{workspace}"
+
+[profiles.prod.storage]
+provider = "mock"
+path = "/tmp"
+"#;
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(toml.as_bytes()).unwrap();
+        let cfg = Config::load(Some(file.path().to_path_buf())).unwrap();
+        let profile = cfg.profile("prod").unwrap();
+        assert_eq!(
+            profile.workspace_config_dir("eu-west-1"),
+            Some(PathBuf::from("/srv/infra/envs/eu-west-1"))
+        );
+        assert_eq!(profile.engine, Engine::Terraform);
+    }
+
+    #[test]
+    fn workspace_timeout_overrides_profile() {
+        let toml = r#"[profiles.prod]
+timeout = "15m"
+
+[profiles.prod.workspaces.slow]
+timeout = "1h"
+
+[profiles.prod.storage]
+provider = "mock"
+path = "/tmp"
+"#;
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(toml.as_bytes()).unwrap();
+        let cfg = Config::load(Some(file.path().to_path_buf())).unwrap();
+        let profile = cfg.profile("prod").unwrap();
+        assert_eq!(
+            profile.workspace_timeout("slow"),
+            Some(Duration::from_secs(3600))
+        );
+        assert_eq!(
+            profile.workspace_timeout("fast"),
+            Some(Duration::from_secs(900))
+        );
+        assert!(parse_duration("10d").is_err());
+        assert!(parse_duration(&format!("{}h", u64::MAX / 60)).is_err());
+
+        // Profile settings placed in a workspace table are an error, not ignored.
+        let misplaced = toml.replace("timeout = \"1h\"", "root_dir = \"infra\"");
+        std::fs::write(file.path(), misplaced).unwrap();
+        let err = Config::load(Some(file.path().to_path_buf())).unwrap_err();
+        assert!(format!("{err:#}").contains("unknown field `root_dir`"));
+    }
+
+    #[test]
+    fn loads_example_config() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/../../terradrift.toml.example");
+        let example = std::fs::read_to_string(path).unwrap();
+        // Storage backends other than mock are behind cargo features.
+        let (profiles, _) = example.split_once("[profiles.prod.storage]").unwrap();
+        let mut file = NamedTempFile::new().unwrap();
+        write!(
+            file,
+            "{profiles}[profiles.prod.storage]\nprovider = \"mock\"\npath = \"/tmp\"\n"
+        )
+        .unwrap();
+        let cfg = Config::load(Some(file.path().to_path_buf())).unwrap();
+        let profile = cfg.profile("prod").unwrap();
+        assert!(profile.root_dir.is_some());
+        assert!(profile.workspace_timeout("network-core").is_some());
+    }
+
+    #[test]
+    fn sinks_with_secret_references() {
+        let toml = r#"[profiles.prod.storage]
+provider = "mock"
+path = "/tmp"
+
+[[profiles.prod.sinks]]
+type = "slack"
+url = "env:TERRADRIFT_TEST_SLACK_URL"
+
+[[profiles.prod.sinks]]
+type = "slack"
+url = "https://hooks.slack.com/services/T0/B0/X"
+when = "error"
+
+[[profiles.prod.sinks]]
+type = "pagerduty"
+routing_key = "env:PD_ROUTING_KEY"
+severity = { update = "warning" }
+"#;
+        let cfg: Config = toml::from_str(toml).unwrap();
+        let sinks = &cfg.profile("prod").unwrap().sinks;
+        assert_eq!(sinks.len(), 3);
+        assert_eq!(sinks[0].when(), NotifyWhen::Drift);
+        assert_eq!(sinks[1].when(), NotifyWhen::Error);
+        assert_eq!(sinks[2].when(), NotifyWhen::Always);
+        let SinkKind::Pagerduty { severity, .. } = &sinks[2].kind else {
+            panic!("expected a PagerDuty sink");
+        };
+        assert_eq!(severity.update, Severity::Warning);
+        assert_eq!(severity.delete, Severity::Critical);
+        assert!(sinks[2].validate().is_ok());
+
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "{toml}when = \"drift\"").unwrap();
+        let err = Config::load(Some(file.path().to_path_buf())).unwrap_err();
+        assert!(format!("{err:#}").contains("when = \"always\""));
+
+        let SinkKind::Slack { url, .. } = &sinks[0].kind else {
+            panic!("expected a Slack sink");
+        };
+        assert!(resolve_secret(url).is_err());
+        std::env::set_var("TERRADRIFT_TEST_SLACK_URL", "https://hooks.example/x");
+        assert_eq!(resolve_secret(url).unwrap(), "https://hooks.example/x");
+        assert_eq!(resolve_secret("plain").unwrap(), "plain");
+    }
 }
@@ -0,0 +1,198 @@
+//! GitHub Actions integration: step summary, annotations and step outputs.
+
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+
+use crate::orchestrator::WorkspaceStatus;
+use crate::report::{changed_label, OutputFormat, RunSummary};
+
+/// Annotations list at most this many resources per workspace.
+const MAX_ANNOTATED_RESOURCES: usize = 10;
+
+/// Files the runner hands a step, from its environment.
+#[derive(Debug, Clone)]
+pub struct ActionsEnv {
+    /// `$GITHUB_STEP_SUMMARY`: Markdown appended to the job summary
+    pub step_summary: Option<PathBuf>,
+    /// `$GITHUB_OUTPUT`: `name=value` step outputs
+    pub output: Option<PathBuf>,
+    /// Where the JSON summary is saved for later steps (`$RUNNER_TEMP`)
+    pub temp_dir: PathBuf,
+}
+
+impl ActionsEnv {
+    /// `Some` when running inside a GitHub Actions job (`GITHUB_ACTIONS=true`).
+    pub fn from_env() -> Option<Self> {
+        if std::env::var("GITHUB_ACTIONS").ok().as_deref() != Some("true") {
+            return None;
+        }
+        let path = |name| std::env::var_os(name).map(PathBuf::from);
+        Some(ActionsEnv {
+            step_summary: path("GITHUB_STEP_SUMMARY"),
+            output: path("GITHUB_OUTPUT"),
+            temp_dir: path("RUNNER_TEMP").unwrap_or_else(std::env::temp_dir),
+        })
+    }
+
+    /// Write the step summary, step outputs and one annotation per drifting or
+    /// failing workspace to `commands`.
+    pub fn report(&self, summary: &RunSummary, commands: &mut impl Write) -> Result<()> {
+        for line in annotations(summary) {
+            writeln!(commands, "{line}")?;
+        }
+
+        if let Some(path) = &self.step_summary {
+            append(path, &summary.render(OutputFormat::Markdown, false)?)?;
+        }
+
+        let summary_path = self
+            .temp_dir
+            .join(format!("terradrift-{}.json", summary.profile));
+        std::fs::write(&summary_path, summary.render(OutputFormat::Json, false)?)
+            .with_context(|| format!("Writing {}", summary_path.display()))?;
+
+        if let Some(path) = &self.output {
+            let drift_count = summary
+                .results
+                .iter()
+                .filter(|r| r.status == WorkspaceStatus::Drift)
+                .count();
+            let error_count = summary
+                .results
+                .iter()
+                .filter(|r| matches!(r.status, WorkspaceStatus::Error | WorkspaceStatus::Timeout))
+                .count();
+            append(
+                path,
+                &format!(
+                    "drift_count={drift_count}\nerror_count={error_count}\nsummary_path={}\n",
+                    summary_path.display()
+                ),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn append(path: &PathBuf, text: &str) -> Result<()> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut f| f.write_all(text.as_bytes()))
+        .with_context(|| format!("Appending to {}", path.display()))
+}
+
+/// `::warning`/`::error` workflow commands for drifting and failing workspaces.
+fn annotations(summary: &RunSummary) -> Vec<String> {
+    summary
+        .results
+        .iter()
+        .filter_map(|r| {
+            let (command, title, mut message) = match r.status {
+                WorkspaceStatus::Clean => return None,
+                WorkspaceStatus::Drift => (
+                    "warning",
+                    format!("Drift in {}/{}", summary.profile, r.workspace),
+                    format!("{} changed resource(s)", changed_label(r)),
+                ),
+                WorkspaceStatus::Timeout => (
+                    "error",
+                    format!("Plan timed out in {}/{}", summary.profile, r.workspace),
+                    r.error.clone().unwrap_or_else(|| "plan timed out".into()),
+                ),
+                WorkspaceStatus::Error => (
+                    "error",
+                    format!("Scan failed in {}/{}", summary.profile, r.workspace),
+                    r.error.clone().unwrap_or_default(),
+                ),
+            };
+            for rc in r.resources.iter().take(MAX_ANNOTATED_RESOURCES) {
+                message.push_str(&format!("\n{} ({})", rc.address, rc.action.as_str()));
+            }
+            if r.resources.len() > MAX_ANNOTATED_RESOURCES {
+                message.push_str(&format!(
+                    "\n… and {} more",
+                    r.resources.len() - MAX_ANNOTATED_RESOURCES
+                ));
+            }
+            Some(format!(
+                "::{command} title={}::{}",
+                escape_property(&title),
+                escape_data(&message)
+            ))
+        })
+        .collect()
+}
+
+/// Escape a workflow command's message.
+fn escape_data(s: &str) -> String {
+    s.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Escape a workflow command property value.
+fn escape_property(s: &str) -> String {
+    escape_data(s).replace(':', "%3A").replace(',', "%2C")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orchestrator::WorkspaceResult;
+    use tempfile::tempdir;
+
+    fn result(workspace: &str, status: WorkspaceStatus) -> WorkspaceResult {
+        WorkspaceResult {
+            duration_ms: 10,
+            error: (status == WorkspaceStatus::Error).then(|| "init failed: 50% done".into()),
+            ..WorkspaceResult::fixture(workspace, status)
+        }
+    }
+
+    #[test]
+    fn writes_summary_outputs_and_annotations() {
+        let dir = tempdir().unwrap();
+        let env = ActionsEnv {
+            step_summary: Some(dir.path().join("summary.md")),
+            output: Some(dir.path().join("output")),
+            temp_dir: dir.path().to_path_buf(),
+        };
+        let summary = RunSummary {
+            profile: "prod".into(),
+            report_url: None,
+            root_dir: None,
+            results: vec![
+                result("app", WorkspaceStatus::Drift),
+                result("net", WorkspaceStatus::Error),
+                result("dns", WorkspaceStatus::Clean),
+            ],
+        };
+
+        let mut commands = Vec::new();
+        env.report(&summary, &mut commands).unwrap();
+        let commands = String::from_utf8(commands).unwrap();
+        assert_eq!(
+            commands,
+            "::warning title=Drift in prod/app::≥1 changed resource(s)\n\
+             ::error title=Scan failed in prod/net::init failed: 50%25 done\n"
+        );
+
+        let md = std::fs::read_to_string(dir.path().join("summary.md")).unwrap();
+        assert!(md.starts_with("### Terradrift · profile `prod`"));
+        let outputs = std::fs::read_to_string(dir.path().join("output")).unwrap();
+        let summary_path = dir.path().join("terradrift-prod.json");
+        assert_eq!(
+            outputs,
+            format!(
+                "drift_count=1\nerror_count=1\nsummary_path={}\n",
+                summary_path.display()
+            )
+        );
+        assert!(summary_path.exists());
+    }
+}
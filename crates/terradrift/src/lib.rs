@@ -1,6 +1,13 @@
 pub mod cli;
 pub mod config;
+pub mod github;
+pub mod metrics;
 pub mod orchestrator;
+pub mod plan;
 pub mod provider;
+pub mod report;
+pub mod signing;
 pub mod sink;
 pub mod terraform;
+pub mod toolchain;
+pub mod ui;
@@ -1,95 +1,132 @@
-use clap::Parser;
-use tokio::runtime::Runtime;
+use std::io::IsTerminal;
 
-use terradrift::cli::{Cli, Commands};
-use terradrift::config::Config;
-use terradrift::orchestrator::run_profile;
-use terradrift::sink::post_slack;
+use anyhow::Context;
+use clap::{CommandFactory, FromArgMatches};
+use tokio::runtime::Runtime;
 
-use tabled::settings::{
-    object::{Columns, Rows},
-    Alignment, Modify, Padding, Style,
-};
-use tabled::{Table, Tabled};
+use terradrift::cli::{output_targets, Cli, Commands};
+use terradrift::config::{Config, NotifyWhen, SinkConfig, SinkKind};
+use terradrift::github::ActionsEnv;
+use terradrift::orchestrator::{exit_code, run_profile, RunOptions};
+use terradrift::report::{OutputFormat, RunSummary};
+use terradrift::sink::notify;
 
 fn main() -> anyhow::Result<()> {
-    let cli = Cli::parse();
+    // Raw matches are kept to pair each --output with its --format.
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
 
     let rt = Runtime::new()?;
     rt.block_on(async {
         match cli.command {
-            Commands::Diff { profile, jobs } => {
+            Commands::Diff {
+                profile,
+                jobs,
+                timeout,
+                full_scan,
+                details,
+                junit,
+                report_url,
+                ..
+            } => {
+                let diff_matches = matches
+                    .subcommand_matches("diff")
+                    .expect("diff subcommand matches");
+                let mut targets = output_targets(diff_matches).map_err(anyhow::Error::msg)?;
+                if targets.is_empty() {
+                    let format = if std::io::stdout().is_terminal() {
+                        OutputFormat::Table
+                    } else {
+                        OutputFormat::Json
+                    };
+                    targets.push((format, None));
+                }
+                if let Some(path) = junit {
+                    targets.push((OutputFormat::Junit, Some(path)));
+                }
+
                 let config = Config::load(cli.config.clone())?;
                 let prof = config.profile(&profile)?;
 
-                let results = run_profile(&profile, prof, jobs).await?;
+                let opts = RunOptions {
+                    jobs,
+                    timeout,
+                    full_scan,
+                };
+                let results = run_profile(&profile, prof, &opts).await?;
 
-                #[derive(Tabled)]
-                struct Row {
-                    #[tabled(rename = "workspace")]
-                    workspace: String,
-                    #[tabled(rename = "Δ")]
-                    drift: String,
-                    #[tabled(rename = "changed")] 
-                    changed: u64,
-                    #[tabled(rename = "ms")]
-                    duration: u128,
+                // diagnostics and stderr of each plan (-v)
+                if cli.verbose > 0 {
+                    for r in &results {
+                        for d in &r.diagnostics {
+                            eprintln!("[{}] {}: {}", r.workspace, d.severity, d.summary);
+                            if let Some(address) = &d.address {
+                                eprintln!("    at {address}");
+                            }
+                            for line in d.detail.lines() {
+                                eprintln!("    {line}");
+                            }
+                        }
+                        if let Some(stderr) = &r.stderr {
+                            eprintln!("[{}] stderr:", r.workspace);
+                            for line in stderr.lines() {
+                                eprintln!("    {line}");
+                            }
+                        }
+                    }
                 }
 
-                let rows: Vec<Row> = results
-                    .iter()
-                    .map(|r| Row {
-                        workspace: r.workspace.clone(),
-                        drift: if r.drift {
-                            "🚨".to_string()
-                        } else {
-                            "✅".to_string()
-                        },
-                        changed: r.changed_resources,
-                        duration: r.duration_ms,
-                    })
-                    .collect();
-
-                let mut table = Table::new(rows);
-                table
-                    .with(Style::modern())
-                    // Align numeric columns right
-                    .with(Modify::new(Columns::single(2)).with(Alignment::right()))
-                    .with(Modify::new(Columns::single(3)).with(Alignment::right()))
-                    // Workspace left-aligned
-                    .with(Modify::new(Columns::single(0)).with(Alignment::left()))
-                    // Center the icon column, no padding
-                    .with(Modify::new(Columns::single(1)).with(Alignment::center()))
-                    .with(Modify::new(Columns::single(1)).with(Padding::zero()))
-                    // Add one-space padding left/right to other columns for readability
-                    .with(Modify::new(Rows::new(0..)).with(Padding::new(1,1,0,0)));
+                let summary = RunSummary {
+                    profile: profile.clone(),
+                    // PLAN_URL predates --report-url
+                    report_url: report_url.or_else(|| std::env::var("PLAN_URL").ok()),
+                    root_dir: prof
+                        .root_dir
+                        .as_ref()
+                        .map(|d| d.canonicalize().unwrap_or_else(|_| d.clone())),
+                    results,
+                };
+                for (format, path) in &targets {
+                    let rendered = summary.render(*format, details)?;
+                    match path {
+                        Some(path) => std::fs::write(path, rendered)
+                            .with_context(|| format!("Writing report to {}", path.display()))?,
+                        None => print!("{rendered}"),
+                    }
+                }
 
-                println!("{}", table);
+                // GitHub Actions: job summary, annotations and step outputs.
+                // Workflow commands go to stderr so stdout stays parseable.
+                // Like metrics and sinks, a failure here must not change the exit code.
+                if let Some(actions) = ActionsEnv::from_env() {
+                    if let Err(e) = actions.report(&summary, &mut std::io::stderr()) {
+                        eprintln!("warning: reporting to GitHub Actions: {e:#}");
+                    }
+                }
 
-                // emit summary json
-                let summary = serde_json::json!({
-                    "profile": profile,
-                    "results": results,
-                });
-                println!("{}", serde_json::to_string_pretty(&summary)?);
+                if let Err(e) = prof.metrics.export(&summary).await {
+                    eprintln!("warning: exporting metrics: {e:#}");
+                }
 
-                // Slack sink (optional)
-                if let Ok(webhook) = std::env::var("SLACK_WEBHOOK_URL") {
-                    let drift_count = results.iter().filter(|r| r.drift).count();
-                    if drift_count > 0 {
-                        let plan_link = std::env::var("PLAN_URL").ok();
-                        let text = if let Some(url) = plan_link {
-                            format!("🚨 Terradrift detected drift in {drift_count} workspace(s) for profile *{profile}*. <{url}|View plan>")
-                        } else {
-                            format!("🚨 Terradrift detected drift in {drift_count} workspace(s) for profile *{profile}*.")
-                        };
-                        let _ = post_slack(&webhook, &text).await;
-                    }
+                // Profiles without sinks keep the SLACK_WEBHOOK_URL alert.
+                let mut sinks = prof.sinks.clone();
+                if sinks.is_empty() && std::env::var_os("SLACK_WEBHOOK_URL").is_some() {
+                    sinks.push(SinkConfig {
+                        kind: SinkKind::Slack {
+                            url: "env:SLACK_WEBHOOK_URL".into(),
+                            plan_url: None,
+                        },
+                        when: Some(NotifyWhen::Drift),
+                    });
+                }
+                for e in notify(&sinks, &summary).await {
+                    eprintln!("warning: notification failed: {e:#}");
                 }
 
-                // Exit code: 0 = no drift, 2 = drift detected
-                if results.iter().any(|r| r.drift) {
-                    std::process::exit(2);
+                // Exit code: 0 = clean, 2 = drift, 1 = errors only, 3 = drift and errors
+                let code = exit_code(&summary.results);
+                if code != 0 {
+                    std::process::exit(code);
                 }
             }
             Commands::Version { json } => {
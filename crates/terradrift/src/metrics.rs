@@ -0,0 +1,299 @@
+//! Prometheus metrics: per-workspace gauges and an error counter, written to a
+//! node_exporter textfile or pushed to a Pushgateway.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+use crate::orchestrator::WorkspaceStatus;
+use crate::report::RunSummary;
+
+/// Pushgateway `job` grouping label.
+const JOB: &str = "terradrift";
+
+/// Where a profile's metrics go (`[profiles.<name>.metrics]`).
+#[derive(Debug, Default, Deserialize)]
+pub struct MetricsConfig {
+    /// node_exporter textfile collector file, replaced atomically on each run
+    pub textfile: Option<std::path::PathBuf>,
+    /// Pushgateway base URL, e.g. `http://pushgateway:9091`
+    pub pushgateway: Option<String>,
+}
+
+impl MetricsConfig {
+    /// Write or push the metrics of `summary` to every configured target.
+    /// `terradrift_scan_errors_total` continues from the target's previous
+    /// values.
+    pub async fn export(&self, summary: &RunSummary) -> Result<()> {
+        if let Some(path) = &self.textfile {
+            let previous = match std::fs::read_to_string(path) {
+                Ok(text) => previous_errors(&text, &summary.profile),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+                Err(e) => {
+                    return Err(e).with_context(|| format!("Reading {}", path.display()));
+                }
+            };
+            write_atomic(path, &render(summary, &previous))?;
+        }
+        if let Some(url) = &self.pushgateway {
+            push(url.trim_end_matches('/'), summary).await?;
+        }
+        Ok(())
+    }
+}
+
+async fn push(base: &str, summary: &RunSummary) -> Result<()> {
+    let client = crate::sink::http_client()?;
+    // The Pushgateway keeps the last push per group; its counters continue
+    // from there.
+    let current = client
+        .get(format!("{base}/metrics"))
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .with_context(|| format!("Reading metrics from Pushgateway {base}"))?
+        .text()
+        .await?;
+    let previous = previous_errors(&current, &summary.profile);
+
+    let url = format!(
+        "{base}/metrics/job/{JOB}/profile/{}",
+        path_segment(&summary.profile)
+    );
+    client
+        .put(&url)
+        .header("Content-Type", "text/plain; version=0.0.4")
+        .body(render(summary, &previous))
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .with_context(|| format!("Pushing metrics to {url}"))?;
+    Ok(())
+}
+
+/// Replace `path` without readers ever seeing a partial file.
+pub(crate) fn write_atomic(path: &Path, contents: &str) -> Result<()> {
+    let name = path
+        .file_name()
+        .with_context(|| format!("{} has no file name", path.display()))?;
+    // Same directory, so the rename cannot cross filesystems; node_exporter
+    // only reads `*.prom`, so it skips the temporary file.
+    let tmp = path.with_file_name(format!(
+        ".{}.{}.tmp",
+        name.to_string_lossy(),
+        std::process::id()
+    ));
+    std::fs::write(&tmp, contents).with_context(|| format!("Writing {}", tmp.display()))?;
+    if let Err(e) = std::fs::rename(&tmp, path) {
+        let _ = std::fs::remove_file(&tmp);
+        return Err(e).with_context(|| format!("Replacing {}", path.display()));
+    }
+    Ok(())
+}
+
+/// Prometheus text exposition of a run. Workspaces that errored before keep
+/// their `terradrift_scan_errors_total` even when absent from this run.
+fn render(summary: &RunSummary, previous_errors: &BTreeMap<String, u64>) -> String {
+    let profile = label_value(&summary.profile);
+    let mut errors = previous_errors.clone();
+    for r in &summary.results {
+        let failed = matches!(r.status, WorkspaceStatus::Error | WorkspaceStatus::Timeout);
+        *errors.entry(r.workspace.clone()).or_default() += u64::from(failed);
+    }
+
+    let mut out = String::new();
+    let mut gauge = |name: &str, help: &str, value: &dyn Fn(usize) -> String| {
+        out.push_str(&format!("# HELP {name} {help}\n# TYPE {name} gauge\n"));
+        for (i, r) in summary.results.iter().enumerate() {
+            out.push_str(&format!(
+                "{name}{{profile=\"{profile}\",workspace=\"{}\"}} {}\n",
+                label_value(&r.workspace),
+                value(i)
+            ));
+        }
+    };
+    let results = &summary.results;
+    gauge(
+        "terradrift_workspace_drift",
+        "Whether the last scan found drift in the workspace (1) or not (0).",
+        &|i| u8::from(results[i].drift).to_string(),
+    );
+    gauge(
+        "terradrift_changed_resources",
+        "Resources the last plan would change (a lower bound in fast scan mode).",
+        &|i| results[i].changed_resources.to_string(),
+    );
+    gauge(
+        "terradrift_scan_duration_seconds",
+        "Duration of the last workspace scan.",
+        &|i| format!("{:.3}", results[i].duration_ms as f64 / 1000.0),
+    );
+
+    out.push_str(
+        "# HELP terradrift_scan_errors_total Workspace scans that failed or timed out.\n\
+         # TYPE terradrift_scan_errors_total counter\n",
+    );
+    for (workspace, count) in &errors {
+        out.push_str(&format!(
+            "terradrift_scan_errors_total{{profile=\"{profile}\",workspace=\"{}\"}} {count}\n",
+            label_value(workspace)
+        ));
+    }
+    out
+}
+
+/// `terradrift_scan_errors_total` per workspace of `profile` in a previous
+/// exposition.
+fn previous_errors(text: &str, profile: &str) -> BTreeMap<String, u64> {
+    let mut errors = BTreeMap::new();
+    for line in text.lines() {
+        let Some(rest) = line.strip_prefix("terradrift_scan_errors_total{") else {
+            continue;
+        };
+        let Some((labels, value)) = rest.rsplit_once('}') else {
+            continue;
+        };
+        let labels = parse_labels(labels);
+        if labels.get("profile").map(String::as_str) != Some(profile) {
+            continue;
+        }
+        let (Some(workspace), Some(value)) = (
+            labels.get("workspace"),
+            value
+                .split_whitespace()
+                .next()
+                .and_then(|v| v.parse::<f64>().ok()),
+        ) else {
+            continue;
+        };
+        errors.insert(workspace.clone(), value as u64);
+    }
+    errors
+}
+
+/// Parse `a="x",b="y"` label pairs.
+fn parse_labels(s: &str) -> BTreeMap<String, String> {
+    let mut labels = BTreeMap::new();
+    let mut chars = s.chars();
+    loop {
+        let name: String = chars
+            .by_ref()
+            .skip_while(|c| *c == ',' || c.is_whitespace())
+            .take_while(|c| *c != '=')
+            .collect();
+        if name.is_empty() || chars.next() != Some('"') {
+            break;
+        }
+        let mut value = String::new();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => break,
+                '\\' => match chars.next() {
+                    Some('n') => value.push('\n'),
+                    Some(c) => value.push(c),
+                    None => break,
+                },
+                c => value.push(c),
+            }
+        }
+        labels.insert(name.trim().to_string(), value);
+    }
+    labels
+}
+
+fn label_value(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Percent-encode a Pushgateway grouping key value for the URL path.
+fn path_segment(s: &str) -> String {
+    s.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            b => format!("%{b:02X}"),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orchestrator::WorkspaceResult;
+    use crate::terraform::ScanMode;
+    use tempfile::tempdir;
+
+    fn summary(statuses: &[(&str, WorkspaceStatus)]) -> RunSummary {
+        RunSummary {
+            profile: "prod".into(),
+            report_url: None,
+            root_dir: None,
+            results: statuses
+                .iter()
+                .map(|(workspace, status)| WorkspaceResult {
+                    scan_mode: ScanMode::Full,
+                    changed_resources: u64::from(*status == WorkspaceStatus::Drift) * 3,
+                    duration_ms: 2500,
+                    ..WorkspaceResult::fixture(workspace, *status)
+                })
+                .collect(),
+        }
+    }
+
+    #[tokio::test]
+    async fn accumulates_errors_across_textfile_writes() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("terradrift.prom");
+        let config = MetricsConfig {
+            textfile: Some(path.clone()),
+            pushgateway: None,
+        };
+
+        let first = summary(&[
+            ("app", WorkspaceStatus::Drift),
+            ("net", WorkspaceStatus::Error),
+        ]);
+        config.export(&first).await.unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert!(text.contains("terradrift_workspace_drift{profile=\"prod\",workspace=\"app\"} 1\n"));
+        assert!(
+            text.contains("terradrift_changed_resources{profile=\"prod\",workspace=\"app\"} 3\n")
+        );
+        assert!(text.contains(
+            "terradrift_scan_duration_seconds{profile=\"prod\",workspace=\"net\"} 2.500\n"
+        ));
+        assert!(
+            text.contains("terradrift_scan_errors_total{profile=\"prod\",workspace=\"net\"} 1\n")
+        );
+
+        let second = summary(&[
+            ("app", WorkspaceStatus::Timeout),
+            ("net", WorkspaceStatus::Error),
+        ]);
+        config.export(&second).await.unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert!(text.contains("terradrift_workspace_drift{profile=\"prod\",workspace=\"app\"} 0\n"));
+        assert!(
+            text.contains("terradrift_scan_errors_total{profile=\"prod\",workspace=\"app\"} 1\n")
+        );
+        assert!(
+            text.contains("terradrift_scan_errors_total{profile=\"prod\",workspace=\"net\"} 2\n")
+        );
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn reads_previous_errors_of_the_profile() {
+        let text = "# TYPE terradrift_scan_errors_total counter\n\
+            terradrift_scan_errors_total{instance=\"\",job=\"terradrift\",profile=\"prod\",workspace=\"a \\\"b\\\"\"} 4\n\
+            terradrift_scan_errors_total{profile=\"staging\",workspace=\"app\"} 7\n";
+        let errors = previous_errors(text, "prod");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors["a \"b\""], 4);
+    }
+}
@@ -1,57 +1,416 @@
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use anyhow::Result;
-use futures::future::join_all;
 use tokio::sync::Semaphore;
 
 use crate::config::Profile;
-use crate::provider::source_from_storage;
-use crate::terraform::{detect_drift, ensure_terraform};
+use crate::plan::{ChangeCounts, DriftCategory, PendingChanges, ResourceChange};
+use crate::provider::{source_from_storage, StateSource};
+use crate::terraform::{
+    config_file, detect_drift, init_plan_dir, prepare_plan_dir, state_terraform_version,
+    CommandFailure, DriftReport, Engine, PlanDir, PlanMode, ScanMode,
+};
+use crate::toolchain::Toolchain;
+use crate::ui::Diagnostic;
+
+/// Knobs for a single `diff` run that can come from the CLI.
+#[derive(Debug, Default, Clone)]
+pub struct RunOptions {
+    /// Limit concurrency (defaults to logical CPU cores)
+    pub jobs: Option<usize>,
+    /// Workspace timeout overriding the profile and per-workspace settings
+    pub timeout: Option<Duration>,
+    /// Read every plan to completion regardless of the profile's `scan_mode`
+    pub full_scan: bool,
+}
+
+/// Outcome of scanning one workspace.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WorkspaceStatus {
+    Clean,
+    Drift,
+    /// The plan was killed after exceeding its timeout.
+    Timeout,
+    /// Fetching the state, resolving the binary, init or plan failed.
+    Error,
+}
+
+impl WorkspaceStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            WorkspaceStatus::Clean => "clean",
+            WorkspaceStatus::Drift => "drift",
+            WorkspaceStatus::Timeout => "timeout",
+            WorkspaceStatus::Error => "error",
+        }
+    }
+}
 
 #[derive(Debug, serde::Serialize)]
 pub struct WorkspaceResult {
     pub workspace: String,
+    pub status: WorkspaceStatus,
+    /// CLI that produced the plan (`terraform` or `tofu`)
+    pub engine: Engine,
+    /// Where the workspace's state was read from
+    pub state: String,
+    /// Configuration directory the plan ran in (unset for plans in the cwd)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub config_dir: Option<PathBuf>,
+    /// File in `config_dir` that code scanning and Code Quality reports point
+    /// at, relative to `root_dir`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub config_file: Option<PathBuf>,
+    /// Version of the engine binary the plan ran with
+    pub terraform_version: String,
     pub drift: bool,
+    /// Whether `changed_resources` and `counts` are exact (`full`) or stop at
+    /// the first change (`fast`)
+    pub scan_mode: ScanMode,
     pub changed_resources: u64,
+    /// Changed resources per action; for `refresh-only` scans, drift only
+    pub counts: ChangeCounts,
+    /// Pending configuration changes, when a normal plan followed a
+    /// `refresh-only` scan; never part of `counts` or `resources`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pending: Option<PendingChanges>,
+    /// Address, type, provider and action of each changed resource
+    pub resources: Vec<ResourceChange>,
     pub duration_ms: u128,
+    /// Why the scan failed (`status: "error"`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Errors and warnings reported by the plan
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub diagnostics: Vec<Diagnostic>,
+    /// Tail of terraform's stderr, when it printed anything
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stderr: Option<String>,
+}
+
+impl WorkspaceResult {
+    /// A scan of `workspace` that produced nothing but `error`.
+    fn errored(
+        workspace: String,
+        engine: Engine,
+        state: String,
+        scan_mode: ScanMode,
+        error: String,
+    ) -> Self {
+        WorkspaceResult {
+            workspace,
+            status: WorkspaceStatus::Error,
+            engine,
+            state,
+            config_dir: None,
+            config_file: None,
+            terraform_version: String::new(),
+            drift: false,
+            scan_mode,
+            changed_resources: 0,
+            counts: ChangeCounts::default(),
+            pending: None,
+            resources: Vec::new(),
+            duration_ms: 0,
+            error: Some(error),
+            diagnostics: Vec::new(),
+            stderr: None,
+        }
+    }
+
+    fn failed(job: &WorkspaceJob, error: &anyhow::Error, start: Instant) -> Self {
+        let failure = error.downcast_ref::<CommandFailure>();
+        let timed_out = failure.is_some_and(|f| f.timed_out);
+        WorkspaceResult {
+            status: if timed_out {
+                WorkspaceStatus::Timeout
+            } else {
+                WorkspaceStatus::Error
+            },
+            config_dir: job.config_dir.clone(),
+            duration_ms: start.elapsed().as_millis(),
+            diagnostics: failure.map(|f| f.diagnostics.clone()).unwrap_or_default(),
+            stderr: failure.and_then(|f| non_empty(&f.stderr)),
+            ..Self::errored(
+                job.workspace.clone(),
+                job.engine,
+                job.source.state_uri(&job.workspace),
+                job.scan_mode,
+                format!("{error:#}"),
+            )
+        }
+    }
+
+    /// A plain result for tests to adjust: drift counts one changed resource.
+    #[cfg(test)]
+    pub(crate) fn fixture(workspace: &str, status: WorkspaceStatus) -> Self {
+        let drift = status == WorkspaceStatus::Drift;
+        WorkspaceResult {
+            status,
+            terraform_version: "1.7.5".into(),
+            drift,
+            changed_resources: u64::from(drift),
+            duration_ms: 1500,
+            error: None,
+            ..Self::errored(
+                workspace.into(),
+                Engine::Terraform,
+                String::new(),
+                ScanMode::Fast,
+                String::new(),
+            )
+        }
+    }
 }
 
 pub async fn run_profile(
     _profile_name: &str,
     profile: &Profile,
-    jobs: Option<usize>,
+    opts: &RunOptions,
 ) -> Result<Vec<WorkspaceResult>> {
     let source = source_from_storage(&profile.storage)?;
     let workspaces = source.list_workspaces().await?;
 
-    let limit = jobs.unwrap_or_else(|| num_cpus::get().max(2));
+    let limit = opts.jobs.unwrap_or_else(|| num_cpus::get().max(2));
     let sem = Arc::new(Semaphore::new(limit));
-    let bin = ensure_terraform(None).await?;
+    let toolchain = Arc::new(
+        Toolchain::new(
+            profile.engine,
+            profile.version_policy,
+            profile.release_keyring.as_deref(),
+        )
+        .await,
+    );
+
+    let scan_mode = if opts.full_scan {
+        ScanMode::Full
+    } else {
+        profile.scan_mode
+    };
 
     let mut handles = Vec::new();
 
     for ws in workspaces {
+        let state = source.state_uri(&ws);
         let permit = sem.clone().acquire_owned().await?;
-        let src = source_from_storage(&profile.storage)?; // new boxed instance
-        let bin_path = bin.clone();
-        let ws_name = ws.clone();
-        handles.push(tokio::spawn(async move {
+        let job = WorkspaceJob {
+            source: source_from_storage(&profile.storage)?, // new boxed instance
+            toolchain: toolchain.clone(),
+            workspace: ws.clone(),
+            engine: profile.engine,
+            root_dir: profile.root_dir.clone(),
+            config_dir: profile.workspace_config_dir(&ws),
+            timeout: opts.timeout.or_else(|| profile.workspace_timeout(&ws)),
+            scan_mode,
+            mode: profile.mode,
+            pending_changes: profile.pending_changes,
+        };
+        let handle = tokio::spawn(async move {
             let _p = permit;
-            let _state = src.fetch_state(&ws_name).await?; // not used yet
-            let report = detect_drift(&bin_path, &_state).await?;
-            Ok::<_, anyhow::Error>(WorkspaceResult {
-                workspace: ws_name,
-                drift: report.drift,
-                changed_resources: report.changed_resources,
-                duration_ms: report.duration_ms,
-            })
-        }));
+            let start = Instant::now();
+            match scan_workspace(&job).await {
+                Ok(result) => result,
+                Err(e) => WorkspaceResult::failed(&job, &e, start),
+            }
+        });
+        handles.push((ws, state, profile.engine, handle));
     }
 
+    // One failing workspace must not hide the others' results.
     let mut results = Vec::new();
-    for res in join_all(handles).await {
-        results.push(res??);
+    for (ws, state, engine, handle) in handles {
+        results.push(match handle.await {
+            Ok(result) => result,
+            Err(e) => WorkspaceResult::errored(
+                ws,
+                engine,
+                state,
+                scan_mode,
+                format!("workspace scan aborted: {e}"),
+            ),
+        });
     }
 
     Ok(results)
 }
+
+/// Everything a spawned task needs to scan one workspace.
+struct WorkspaceJob {
+    source: Box<dyn StateSource>,
+    toolchain: Arc<Toolchain>,
+    workspace: String,
+    engine: Engine,
+    root_dir: Option<PathBuf>,
+    config_dir: Option<PathBuf>,
+    timeout: Option<Duration>,
+    scan_mode: ScanMode,
+    mode: PlanMode,
+    pending_changes: bool,
+}
+
+async fn scan_workspace(job: &WorkspaceJob) -> Result<WorkspaceResult> {
+    let state = job.source.fetch_state(&job.workspace).await?;
+    let state_version = state_terraform_version(&state).await?;
+    let bin_path = job.toolchain.binary_for(state_version.as_deref()).await?;
+    // One deadline for init and plan: provider downloads hang as readily as plans.
+    // A timeout too long to represent is no limit at all.
+    let deadline = job.timeout.and_then(|t| Instant::now().checked_add(t));
+    let plan_dir = match (&job.root_dir, &job.config_dir) {
+        (Some(root), Some(dir)) => {
+            let plan_dir = prepare_plan_dir(root, dir, &state).await?;
+            if let Err(e) = init_plan_dir(&bin_path, &plan_dir, remaining(deadline)).await {
+                plan_dir.cleanup().await;
+                return Err(e);
+            }
+            plan_dir
+        }
+        _ => PlanDir::current(&state),
+    };
+    let report = match job.mode {
+        PlanMode::Normal => {
+            detect_drift(
+                &bin_path,
+                &plan_dir,
+                remaining(deadline),
+                job.scan_mode,
+                PlanMode::Normal,
+            )
+            .await
+        }
+        PlanMode::RefreshOnly => classify_drift(&bin_path, &plan_dir, job, deadline).await,
+    };
+    plan_dir.cleanup().await;
+    let report = report?;
+    let status = if report.timed_out {
+        WorkspaceStatus::Timeout
+    } else if report.drift {
+        WorkspaceStatus::Drift
+    } else {
+        WorkspaceStatus::Clean
+    };
+    Ok(WorkspaceResult {
+        workspace: job.workspace.clone(),
+        status,
+        engine: job.engine,
+        state: job.source.state_uri(&job.workspace),
+        config_dir: job.config_dir.clone(),
+        config_file: job
+            .root_dir
+            .as_deref()
+            .zip(job.config_dir.as_deref())
+            .and_then(|(root, dir)| config_file(root, dir)),
+        terraform_version: report.terraform_version,
+        drift: report.drift,
+        scan_mode: job.scan_mode,
+        changed_resources: report.changed_resources,
+        counts: report.counts,
+        pending: report.pending,
+        resources: report.resources,
+        duration_ms: report.duration_ms,
+        error: None,
+        stderr: non_empty(&report.stderr),
+        diagnostics: report.diagnostics,
+    })
+}
+
+/// Time left until `deadline`, if there is one.
+fn remaining(deadline: Option<Instant>) -> Option<Duration> {
+    deadline.map(|d| d.saturating_duration_since(Instant::now()))
+}
+
+fn non_empty(s: &str) -> Option<String> {
+    (!s.is_empty()).then(|| s.to_string())
+}
+
+/// Refresh-only plan for changes made outside Terraform, optionally followed by
+/// a normal plan whose other changes are pending configuration. Only the
+/// former counts as drift.
+async fn classify_drift(
+    bin: &Path,
+    plan_dir: &PlanDir,
+    job: &WorkspaceJob,
+    deadline: Option<Instant>,
+) -> Result<DriftReport> {
+    let mut report = detect_drift(
+        bin,
+        plan_dir,
+        remaining(deadline),
+        job.scan_mode,
+        PlanMode::RefreshOnly,
+    )
+    .await?;
+    for rc in &mut report.resources {
+        rc.category = Some(DriftCategory::InfrastructureDrift);
+    }
+    if !job.pending_changes || report.timed_out {
+        return Ok(report);
+    }
+
+    // Read to completion: a fast scan would stop at the drift reported again
+    // ahead of the planned changes.
+    let plan = detect_drift(
+        bin,
+        plan_dir,
+        remaining(deadline),
+        ScanMode::Full,
+        PlanMode::Normal,
+    )
+    .await?;
+    let pending: Vec<ResourceChange> = plan
+        .resources
+        .into_iter()
+        .filter(|rc| !report.resources.iter().any(|r| r.address == rc.address))
+        .map(|rc| ResourceChange {
+            category: Some(DriftCategory::PendingConfigChange),
+            ..rc
+        })
+        .collect();
+    report.pending = Some(PendingChanges {
+        counts: ChangeCounts::from_resources(&pending),
+        resources: pending,
+    });
+    report.timed_out = plan.timed_out;
+    report.duration_ms += plan.duration_ms;
+    report.diagnostics.extend(plan.diagnostics);
+    if !plan.stderr.is_empty() {
+        report.stderr = plan.stderr;
+    }
+    Ok(report)
+}
+
+/// Process exit code for a run: 0 clean, 2 drift, 1 errors only, 3 drift and
+/// errors. Timeouts count as errors.
+pub fn exit_code(results: &[WorkspaceResult]) -> i32 {
+    let drift = results.iter().any(|r| r.status == WorkspaceStatus::Drift);
+    let errors = results
+        .iter()
+        .any(|r| matches!(r.status, WorkspaceStatus::Error | WorkspaceStatus::Timeout));
+    match (drift, errors) {
+        (false, false) => 0,
+        (true, false) => 2,
+        (false, true) => 1,
+        (true, true) => 3,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(status: WorkspaceStatus) -> WorkspaceResult {
+        WorkspaceResult::fixture("ws", status)
+    }
+
+    #[test]
+    fn exit_codes() {
+        use WorkspaceStatus::*;
+        assert_eq!(exit_code(&[result(Clean)]), 0);
+        assert_eq!(exit_code(&[result(Clean), result(Drift)]), 2);
+        assert_eq!(exit_code(&[result(Error), result(Clean)]), 1);
+        assert_eq!(exit_code(&[result(Timeout)]), 1);
+        assert_eq!(exit_code(&[result(Drift), result(Error)]), 3);
+    }
+}
@@ -0,0 +1,527 @@
+use serde::Serialize;
+use serde_json::Value;
+
+/// What a plan would do to a drifted resource.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeAction {
+    Create,
+    Read,
+    Update,
+    Delete,
+    /// Delete-then-create or create-then-delete.
+    Replace,
+    /// Removed from state without destroying the object (Terraform 1.7+).
+    Forget,
+}
+
+impl ChangeAction {
+    /// Summarise a plan's `change.actions` list; `None` for no-ops and
+    /// anything unrecognised.
+    pub fn from_actions<'a>(actions: impl IntoIterator<Item = &'a str>) -> Option<Self> {
+        let actions: Vec<&str> = actions.into_iter().collect();
+        match actions.as_slice() {
+            ["create"] => Some(ChangeAction::Create),
+            ["read"] => Some(ChangeAction::Read),
+            ["update"] => Some(ChangeAction::Update),
+            ["delete"] => Some(ChangeAction::Delete),
+            ["forget"] => Some(ChangeAction::Forget),
+            ["delete", "create"] | ["create", "delete"] => Some(ChangeAction::Replace),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ChangeAction::Create => "create",
+            ChangeAction::Read => "read",
+            ChangeAction::Update => "update",
+            ChangeAction::Delete => "delete",
+            ChangeAction::Replace => "replace",
+            ChangeAction::Forget => "forget",
+        }
+    }
+}
+
+/// One resource a plan would change.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ResourceChange {
+    /// Full address, e.g. `module.net.aws_subnet.private[0]`
+    pub address: String,
+    /// Containing module (`None` for the root module)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub module_address: Option<String>,
+    pub resource_type: String,
+    /// Provider source address, e.g. `registry.terraform.io/hashicorp/aws`
+    pub provider: String,
+    pub action: ChangeAction,
+    /// Why Terraform chose this action, e.g. `replace_because_cannot_update`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub action_reason: Option<String>,
+    /// Attribute paths whose value differs between `before` and `after`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub attributes: Vec<AttributeChange>,
+    /// Where the change comes from; only known for `refresh-only` scans
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub category: Option<DriftCategory>,
+}
+
+/// Why a resource shows up in a plan.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DriftCategory {
+    /// The real object was changed outside Terraform.
+    InfrastructureDrift,
+    /// The configuration has changes that were never applied.
+    PendingConfigChange,
+}
+
+impl DriftCategory {
+    /// Short form for tables.
+    pub fn label(self) -> &'static str {
+        match self {
+            DriftCategory::InfrastructureDrift => "drift",
+            DriftCategory::PendingConfigChange => "pending",
+        }
+    }
+}
+
+/// Changed resources per plan action.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct ChangeCounts {
+    pub create: u64,
+    pub update: u64,
+    pub delete: u64,
+    pub replace: u64,
+}
+
+/// Changes a normal plan would make on top of refresh-only drift. Kept apart
+/// from `resources` so nothing alerts on them.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct PendingChanges {
+    #[serde(flatten)]
+    pub counts: ChangeCounts,
+    pub resources: Vec<ResourceChange>,
+}
+
+impl ChangeCounts {
+    pub fn from_resources(resources: &[ResourceChange]) -> Self {
+        let mut counts = ChangeCounts::default();
+        for rc in resources {
+            counts.record(rc.action);
+        }
+        counts
+    }
+
+    /// Count one resource; reads and forgets change nothing.
+    pub fn record(&mut self, action: ChangeAction) {
+        match action {
+            ChangeAction::Create => self.create += 1,
+            ChangeAction::Update => self.update += 1,
+            ChangeAction::Delete => self.delete += 1,
+            ChangeAction::Replace => self.replace += 1,
+            ChangeAction::Read | ChangeAction::Forget => {}
+        }
+    }
+
+    /// Resources changed by any action.
+    pub fn total(&self) -> u64 {
+        self.create + self.update + self.delete + self.replace
+    }
+}
+
+/// Placeholder for values Terraform marks sensitive.
+pub const SENSITIVE: &str = "(sensitive value)";
+/// Placeholder for values only known after apply.
+pub const UNKNOWN: &str = "(known after apply)";
+
+/// One attribute that differs between a resource's `before` and `after`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AttributeChange {
+    /// Attribute path, e.g. `tags.CostCenter` or `ingress[0].cidr_blocks`
+    pub path: String,
+    /// Previous value (`null` when absent)
+    pub old: Value,
+    /// Planned value (`null` when removed)
+    pub new: Value,
+    /// Values were redacted because either side is sensitive
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub sensitive: bool,
+    /// The planned value is only known after apply
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub unknown: bool,
+}
+
+/// The parts of a plan's `change` object attribute diffs are computed from.
+struct ChangeValues<'a> {
+    before: &'a Value,
+    after: &'a Value,
+    after_unknown: &'a Value,
+    before_sensitive: &'a Value,
+    after_sensitive: &'a Value,
+}
+
+impl<'a> ChangeValues<'a> {
+    /// Descend into an object key or array index on every side at once.
+    fn child(&self, step: &Step) -> ChangeValues<'a> {
+        ChangeValues {
+            before: step.index(self.before),
+            after: step.index(self.after),
+            after_unknown: step.index(self.after_unknown),
+            before_sensitive: step.index(self.before_sensitive),
+            after_sensitive: step.index(self.after_sensitive),
+        }
+    }
+}
+
+enum Step {
+    Key(String),
+    Index(usize),
+}
+
+impl Step {
+    fn index<'v>(&self, value: &'v Value) -> &'v Value {
+        match self {
+            Step::Key(k) => &value[k.as_str()],
+            Step::Index(i) => &value[*i],
+        }
+    }
+}
+
+/// Compute the attribute-level diff of a plan `change` object, redacting
+/// sensitive paths and marking values unknown until apply.
+pub fn attribute_changes(change: &Value) -> Vec<AttributeChange> {
+    let values = ChangeValues {
+        before: &change["before"],
+        after: &change["after"],
+        after_unknown: &change["after_unknown"],
+        before_sensitive: &change["before_sensitive"],
+        after_sensitive: &change["after_sensitive"],
+    };
+    let mut out = Vec::new();
+    diff_values(&values, &mut Vec::new(), false, &mut out);
+    out
+}
+
+fn diff_values(
+    v: &ChangeValues<'_>,
+    path: &mut Vec<Step>,
+    sensitive: bool,
+    out: &mut Vec<AttributeChange>,
+) {
+    let sensitive = sensitive
+        || v.before_sensitive == &Value::Bool(true)
+        || v.after_sensitive == &Value::Bool(true);
+    let unknown = v.after_unknown == &Value::Bool(true);
+
+    if !unknown {
+        let steps: Option<Vec<Step>> = match (v.before, v.after) {
+            (Value::Object(b), Value::Object(a)) => {
+                let mut keys: Vec<&String> = b.keys().chain(a.keys()).collect();
+                keys.sort();
+                keys.dedup();
+                Some(keys.into_iter().map(|k| Step::Key(k.clone())).collect())
+            }
+            (Value::Array(b), Value::Array(a)) => {
+                Some((0..b.len().max(a.len())).map(Step::Index).collect())
+            }
+            _ => None,
+        };
+        if let Some(steps) = steps {
+            for step in steps {
+                let child = v.child(&step);
+                path.push(step);
+                diff_values(&child, path, sensitive, out);
+                path.pop();
+            }
+            return;
+        }
+        if v.before == v.after {
+            return;
+        }
+    }
+
+    // One side may still be a whole object or list (a create, a delete, a
+    // block appearing); its leaves carry their own masks.
+    let mut redacted = false;
+    let old = masked(
+        v.before,
+        v.before_sensitive,
+        &Value::Null,
+        sensitive,
+        &mut redacted,
+    );
+    let new = if unknown {
+        Value::String(UNKNOWN.to_string())
+    } else {
+        masked(
+            v.after,
+            v.after_sensitive,
+            v.after_unknown,
+            sensitive,
+            &mut redacted,
+        )
+    };
+    out.push(AttributeChange {
+        path: format_path(path),
+        old,
+        new,
+        sensitive: sensitive || redacted,
+        unknown,
+    });
+}
+
+/// `value` with every leaf under a `true` sensitive mask replaced by
+/// [`SENSITIVE`] and every path under a `true` unknown mask by [`UNKNOWN`].
+fn masked(
+    value: &Value,
+    sensitive_mask: &Value,
+    unknown_mask: &Value,
+    sensitive: bool,
+    redacted: &mut bool,
+) -> Value {
+    let sensitive = sensitive || sensitive_mask == &Value::Bool(true);
+    if unknown_mask == &Value::Bool(true) {
+        return Value::String(UNKNOWN.to_string());
+    }
+    match value {
+        Value::Object(map) => {
+            // Terraform omits unknown attributes from `after` entirely.
+            let unknown_keys = unknown_mask
+                .as_object()
+                .into_iter()
+                .flatten()
+                .filter(|(_, u)| u == &&Value::Bool(true))
+                .map(|(k, _)| k);
+            let mut keys: Vec<&String> = map.keys().chain(unknown_keys).collect();
+            keys.sort();
+            keys.dedup();
+            keys.into_iter()
+                .map(|k| {
+                    let child = masked(
+                        &value[k.as_str()],
+                        &sensitive_mask[k.as_str()],
+                        &unknown_mask[k.as_str()],
+                        sensitive,
+                        redacted,
+                    );
+                    (k.clone(), child)
+                })
+                .collect::<serde_json::Map<_, _>>()
+                .into()
+        }
+        Value::Array(items) => (0..items.len())
+            .map(|i| {
+                masked(
+                    &items[i],
+                    &sensitive_mask[i],
+                    &unknown_mask[i],
+                    sensitive,
+                    redacted,
+                )
+            })
+            .collect::<Vec<_>>()
+            .into(),
+        Value::Null => Value::Null,
+        _ if sensitive => {
+            *redacted = true;
+            Value::String(SENSITIVE.to_string())
+        }
+        leaf => leaf.clone(),
+    }
+}
+
+fn format_path(path: &[Step]) -> String {
+    let mut out = String::new();
+    for step in path {
+        match step {
+            Step::Index(i) => out.push_str(&format!("[{i}]")),
+            Step::Key(k) if is_identifier(k) => {
+                if !out.is_empty() {
+                    out.push('.');
+                }
+                out.push_str(k);
+            }
+            Step::Key(k) => out.push_str(&format!("[{}]", Value::String(k.clone()))),
+        }
+    }
+    out
+}
+
+fn is_identifier(key: &str) -> bool {
+    !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+impl ResourceChange {
+    /// Parse one entry of a JSON plan's `resource_changes` array. No-op
+    /// entries yield `None`.
+    pub fn from_plan_json(rc: &Value) -> Option<Self> {
+        let actions = rc["change"]["actions"].as_array()?;
+        let action = ChangeAction::from_actions(actions.iter().filter_map(Value::as_str))?;
+        let text = |key: &str| rc[key].as_str().map(str::to_string);
+        Some(ResourceChange {
+            address: text("address").unwrap_or_default(),
+            module_address: text("module_address"),
+            resource_type: text("type").unwrap_or_default(),
+            provider: text("provider_name").unwrap_or_default(),
+            action,
+            action_reason: text("action_reason"),
+            attributes: attribute_changes(&rc["change"]),
+            category: None,
+        })
+    }
+
+    /// A change to `address`, e.g. `aws_s3_bucket.logs`, for tests.
+    #[cfg(test)]
+    pub(crate) fn fixture(address: &str, action: ChangeAction) -> Self {
+        let resource_type = address.split('.').next().unwrap_or_default();
+        ResourceChange {
+            address: address.into(),
+            module_address: None,
+            resource_type: resource_type.into(),
+            provider: resource_type.split('_').next().unwrap_or_default().into(),
+            action,
+            action_reason: None,
+            attributes: Vec::new(),
+            category: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_resource_change() {
+        let rc = serde_json::json!({
+            "address": "module.net.aws_subnet.private[0]",
+            "module_address": "module.net",
+            "mode": "managed",
+            "type": "aws_subnet",
+            "name": "private",
+            "index": 0,
+            "provider_name": "registry.terraform.io/hashicorp/aws",
+            "change": { "actions": ["delete", "create"] },
+            "action_reason": "replace_because_cannot_update"
+        });
+        let change = ResourceChange::from_plan_json(&rc).unwrap();
+        assert_eq!(change.address, "module.net.aws_subnet.private[0]");
+        assert_eq!(change.module_address.as_deref(), Some("module.net"));
+        assert_eq!(change.resource_type, "aws_subnet");
+        assert_eq!(change.action, ChangeAction::Replace);
+        assert_eq!(
+            change.action_reason.as_deref(),
+            Some("replace_because_cannot_update")
+        );
+
+        assert!(change.attributes.is_empty());
+
+        let noop = serde_json::json!({ "address": "x.y", "change": { "actions": ["no-op"] } });
+        assert!(ResourceChange::from_plan_json(&noop).is_none());
+    }
+
+    #[test]
+    fn attribute_diff_redacts_sensitive_and_marks_unknown() {
+        let change = serde_json::json!({
+            "actions": ["update"],
+            "before": {
+                "tags": { "CostCenter": "1234", "Team": "core" },
+                "password": "hunter2",
+                "arn": "arn:aws:x",
+                "ports": [80, 443]
+            },
+            "after": {
+                "tags": { "CostCenter": "5678", "Team": "core", "kubernetes.io/role": "x" },
+                "password": "hunter3",
+                "ports": [80, 8443]
+            },
+            "after_unknown": { "arn": true, "tags": {}, "ports": [false, false] },
+            "before_sensitive": { "password": true },
+            "after_sensitive": { "password": true }
+        });
+        let attrs = attribute_changes(&change);
+        let paths: Vec<&str> = attrs.iter().map(|a| a.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            [
+                "arn",
+                "password",
+                "ports[1]",
+                "tags.CostCenter",
+                "tags[\"kubernetes.io/role\"]"
+            ]
+        );
+
+        assert!(attrs[0].unknown);
+        assert_eq!(attrs[0].new, UNKNOWN);
+        assert!(attrs[1].sensitive);
+        assert_eq!(attrs[1].old, SENSITIVE);
+        assert_eq!(attrs[1].new, SENSITIVE);
+        assert_eq!(attrs[3].old, "1234");
+        assert_eq!(attrs[3].new, "5678");
+        assert_eq!(attrs[4].old, Value::Null);
+
+        // Created, deleted and newly set blocks are reported whole; their
+        // sensitive leaves are still redacted.
+        let create = serde_json::json!({
+            "actions": ["create"],
+            "before": null,
+            "after": { "name": "db", "password": "hunter2", "tags": { "Team": "core" } },
+            "after_unknown": { "id": true, "tags": {} },
+            "before_sensitive": false,
+            "after_sensitive": { "password": true, "tags": {} }
+        });
+        let attrs = attribute_changes(&create);
+        assert_eq!(attrs.len(), 1);
+        assert!(attrs[0].sensitive);
+        assert!(!attrs[0].unknown);
+        assert_eq!(attrs[0].old, Value::Null);
+        assert_eq!(
+            attrs[0].new,
+            serde_json::json!({
+                "id": UNKNOWN,
+                "name": "db",
+                "password": SENSITIVE,
+                "tags": { "Team": "core" }
+            })
+        );
+
+        let delete = serde_json::json!({
+            "actions": ["delete"],
+            "before": { "name": "db", "users": [{ "name": "app", "secret": "s3cr3t" }] },
+            "after": null,
+            "after_unknown": false,
+            "before_sensitive": { "users": [{ "secret": true }] },
+            "after_sensitive": false
+        });
+        let attrs = attribute_changes(&delete);
+        assert_eq!(attrs.len(), 1);
+        assert!(attrs[0].sensitive);
+        assert_eq!(
+            attrs[0].old,
+            serde_json::json!({ "name": "db", "users": [{ "name": "app", "secret": SENSITIVE }] })
+        );
+        assert_eq!(attrs[0].new, Value::Null);
+
+        let nested = serde_json::json!({
+            "actions": ["update"],
+            "before": { "name": "db", "auth": null },
+            "after": { "name": "db", "auth": { "user": "admin", "token": "t0k3n" } },
+            "after_unknown": { "auth": { "expires": true } },
+            "before_sensitive": {},
+            "after_sensitive": { "auth": { "token": true } }
+        });
+        let attrs = attribute_changes(&nested);
+        assert_eq!(attrs.len(), 1);
+        assert_eq!(attrs[0].path, "auth");
+        assert!(attrs[0].sensitive);
+        assert_eq!(
+            attrs[0].new,
+            serde_json::json!({ "expires": UNKNOWN, "token": SENSITIVE, "user": "admin" })
+        );
+    }
+}
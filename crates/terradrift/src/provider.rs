@@ -31,6 +31,18 @@ pub trait StateSource: Send + Sync {
 
     /// List available workspaces under this source.
     async fn list_workspaces(&self) -> Result<Vec<String>>;
+
+    /// Where `workspace`'s state lives, e.g. `s3://bucket/prefix/ws.tfstate`.
+    fn state_uri(&self, workspace: &str) -> String;
+}
+
+/// Object key of a workspace's state under an optional prefix.
+#[cfg(any(feature = "s3", feature = "gcs", feature = "azure"))]
+fn state_key(prefix: &Option<String>, workspace: &str) -> String {
+    match prefix {
+        Some(p) => format!("{}/{}.tfstate", p.trim_end_matches('/'), workspace),
+        None => format!("{}.tfstate", workspace),
+    }
 }
 
 pub fn source_from_storage(storage: &Storage) -> Result<Box<dyn StateSource>> {
@@ -91,6 +103,13 @@ impl StateSource for MockStateSource {
         }
         Ok(list)
     }
+
+    fn state_uri(&self, workspace: &str) -> String {
+        self.root
+            .join(format!("{workspace}.tfstate"))
+            .display()
+            .to_string()
+    }
 }
 
 #[cfg(feature = "s3")]
@@ -103,10 +122,7 @@ struct S3StateSource {
 #[async_trait]
 impl StateSource for S3StateSource {
     async fn fetch_state(&self, workspace: &str) -> Result<PathBuf> {
-        let key = match &self.prefix {
-            Some(p) => format!("{}/{}.tfstate", p.trim_end_matches('/'), workspace),
-            None => format!("{}.tfstate", workspace),
-        };
+        let key = state_key(&self.prefix, workspace);
 
         let aws_cfg = aws_config::load_defaults(BehaviorVersion::latest()).await;
         let client = S3Client::new(&aws_cfg);
@@ -163,6 +179,14 @@ impl StateSource for S3StateSource {
         }
         Ok(out)
     }
+
+    fn state_uri(&self, workspace: &str) -> String {
+        format!(
+            "s3://{}/{}",
+            self.bucket,
+            state_key(&self.prefix, workspace)
+        )
+    }
 }
 
 #[cfg(feature = "gcs")]
@@ -190,10 +214,7 @@ struct Obj {
 #[async_trait]
 impl StateSource for GcsStateSource {
     async fn fetch_state(&self, workspace: &str) -> Result<PathBuf> {
-        let obj_name = match &self.prefix {
-            Some(p) => format!("{}/{}.tfstate", p.trim_end_matches('/'), workspace),
-            None => format!("{}.tfstate", workspace),
-        };
+        let obj_name = state_key(&self.prefix, workspace);
 
         let provider = gcp_auth::provider().await?;
         let token = provider
@@ -277,6 +298,14 @@ impl StateSource for GcsStateSource {
         }
         Ok(out)
     }
+
+    fn state_uri(&self, workspace: &str) -> String {
+        format!(
+            "gs://{}/{}",
+            self.bucket,
+            state_key(&self.prefix, workspace)
+        )
+    }
 }
 
 #[cfg(feature = "azure")]
@@ -302,10 +331,7 @@ impl StateSource for AzureStateSource {
         let service = ClientBuilder::new(account, credentials);
         let container = service.container_client(&self.container);
 
-        let key = match &self.prefix {
-            Some(p) => format!("{}/{}.tfstate", p.trim_end_matches('/'), _workspace),
-            None => format!("{}.tfstate", _workspace),
-        };
+        let key = state_key(&self.prefix, _workspace);
 
         let blob = container.blob_client(&key);
         let bytes = blob
@@ -354,6 +380,14 @@ impl StateSource for AzureStateSource {
         }
         Ok(out)
     }
+
+    fn state_uri(&self, workspace: &str) -> String {
+        format!(
+            "azure://{}/{}",
+            self.container,
+            state_key(&self.prefix, workspace)
+        )
+    }
 }
 
 #[cfg(test)]
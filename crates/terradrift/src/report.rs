@@ -0,0 +1,850 @@
+//! Renders a run's results in the formats `--format` selects.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use tabled::settings::{
+    object::{Columns, Rows},
+    Alignment, Modify, Padding, Style,
+};
+use tabled::{Table, Tabled};
+
+use crate::orchestrator::{WorkspaceResult, WorkspaceStatus};
+use crate::plan::{ChangeAction, ResourceChange};
+use crate::terraform::ScanMode;
+
+mod html;
+
+/// Output format of a run summary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// Human-readable table
+    Table,
+    /// One pretty-printed JSON document
+    Json,
+    /// One JSON object per workspace and line
+    Ndjson,
+    /// GitHub-flavoured Markdown tables
+    Markdown,
+    /// One row per workspace
+    Csv,
+    /// JUnit XML: one testcase per workspace, one suite per profile
+    Junit,
+    /// SARIF 2.1.0 for code scanning: one result per drifted resource
+    Sarif,
+    /// GitLab Code Quality: one issue per drifted resource
+    GitlabCodequality,
+    /// Self-contained HTML page with a sortable table and expandable diffs
+    Html,
+}
+
+/// Everything a run produced, as written by every output format.
+#[derive(Debug, Serialize)]
+pub struct RunSummary {
+    pub profile: String,
+    /// Where the HTML report is published (`--report-url`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub report_url: Option<String>,
+    /// Absolute configuration root of the profile; each result's
+    /// `config_file` is relative to it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub root_dir: Option<PathBuf>,
+    pub results: Vec<WorkspaceResult>,
+}
+
+impl RunSummary {
+    /// Render in `format`. `details` adds the per-resource table to `table`
+    /// output; the other formats always carry resources.
+    pub fn render(&self, format: OutputFormat, details: bool) -> Result<String> {
+        Ok(match format {
+            OutputFormat::Table => render_table(self, details),
+            OutputFormat::Json => serde_json::to_string_pretty(self)? + "\n",
+            OutputFormat::Ndjson => render_ndjson(self)?,
+            OutputFormat::Markdown => render_markdown(self),
+            OutputFormat::Csv => render_csv(self),
+            OutputFormat::Junit => render_junit(self),
+            OutputFormat::Sarif => serde_json::to_string_pretty(&sarif(self))? + "\n",
+            OutputFormat::GitlabCodequality => {
+                serde_json::to_string_pretty(&gitlab_codequality(self))? + "\n"
+            }
+            OutputFormat::Html => html::render_html(self),
+        })
+    }
+}
+
+pub fn status_icon(status: WorkspaceStatus) -> &'static str {
+    match status {
+        WorkspaceStatus::Drift => "🚨",
+        WorkspaceStatus::Timeout => "⏱",
+        WorkspaceStatus::Error => "❌",
+        WorkspaceStatus::Clean => "✅",
+    }
+}
+
+/// `changed_resources` annotated with how exact it is.
+pub fn changed_label(r: &WorkspaceResult) -> String {
+    match r.scan_mode {
+        // Fast scans stop at the first change: a lower bound.
+        ScanMode::Fast if r.drift => format!("≥{}", r.changed_resources),
+        ScanMode::Fast => r.changed_resources.to_string(),
+        ScanMode::Full => format!(
+            "{} (+{} ~{} -{} ±{})",
+            r.changed_resources,
+            r.counts.create,
+            r.counts.update,
+            r.counts.delete,
+            r.counts.replace
+        ),
+    }
+}
+
+fn render_table(summary: &RunSummary, details: bool) -> String {
+    #[derive(Tabled)]
+    struct Row {
+        #[tabled(rename = "workspace")]
+        workspace: String,
+        #[tabled(rename = "Δ")]
+        drift: String,
+        #[tabled(rename = "changed")]
+        changed: String,
+        #[tabled(rename = "ms")]
+        duration: u128,
+    }
+
+    let rows: Vec<Row> = summary
+        .results
+        .iter()
+        .map(|r| Row {
+            workspace: r.workspace.clone(),
+            drift: status_icon(r.status).to_string(),
+            changed: changed_label(r),
+            duration: r.duration_ms,
+        })
+        .collect();
+
+    let mut table = Table::new(rows);
+    table
+        .with(Style::modern())
+        // Align numeric columns right
+        .with(Modify::new(Columns::single(2)).with(Alignment::right()))
+        .with(Modify::new(Columns::single(3)).with(Alignment::right()))
+        // Workspace left-aligned
+        .with(Modify::new(Columns::single(0)).with(Alignment::left()))
+        // Center the icon column, no padding
+        .with(Modify::new(Columns::single(1)).with(Alignment::center()))
+        .with(Modify::new(Columns::single(1)).with(Padding::zero()))
+        // Add one-space padding left/right to other columns for readability
+        .with(Modify::new(Rows::new(0..)).with(Padding::new(1, 1, 0, 0)));
+    let mut out = format!("{table}\n");
+
+    if details {
+        #[derive(Tabled)]
+        struct ResourceRow {
+            workspace: String,
+            address: String,
+            action: String,
+            #[tabled(rename = "type")]
+            resource_type: String,
+            provider: String,
+            reason: String,
+            category: String,
+            attributes: String,
+        }
+
+        let rows: Vec<ResourceRow> = summary
+            .results
+            .iter()
+            .flat_map(|r| {
+                r.resources.iter().map(|rc| ResourceRow {
+                    workspace: r.workspace.clone(),
+                    address: rc.address.clone(),
+                    action: rc.action.as_str().to_string(),
+                    resource_type: rc.resource_type.clone(),
+                    provider: rc.provider.clone(),
+                    reason: rc.action_reason.clone().unwrap_or_default(),
+                    category: rc.category.map(|c| c.label()).unwrap_or_default().into(),
+                    attributes: rc
+                        .attributes
+                        .iter()
+                        .map(|a| format!("{}: {} → {}", a.path, a.old, a.new))
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                })
+            })
+            .collect();
+        if !rows.is_empty() {
+            let mut table = Table::new(rows);
+            table.with(Style::modern());
+            out.push_str(&format!("{table}\n"));
+        }
+    }
+    out
+}
+
+fn render_ndjson(summary: &RunSummary) -> Result<String> {
+    let mut out = String::new();
+    for r in &summary.results {
+        let mut line = serde_json::Map::new();
+        line.insert("profile".into(), summary.profile.clone().into());
+        if let serde_json::Value::Object(fields) = serde_json::to_value(r)? {
+            line.extend(fields);
+        }
+        out.push_str(&serde_json::to_string(&line)?);
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+/// Escape a value for a Markdown table cell.
+fn md_cell(s: &str) -> String {
+    s.replace('|', "\\|").replace('\n', "<br>")
+}
+
+fn render_markdown(summary: &RunSummary) -> String {
+    let mut out = format!("### Terradrift · profile `{}`\n\n", summary.profile);
+    out.push_str("| Workspace | Status | Changed | Engine | Duration |\n");
+    out.push_str("|---|---|---:|---|---:|\n");
+    for r in &summary.results {
+        out.push_str(&format!(
+            "| {} | {} {} | {} | {} {} | {:.1}s |\n",
+            md_cell(&r.workspace),
+            status_icon(r.status),
+            r.status.as_str(),
+            changed_label(r),
+            r.engine,
+            r.terraform_version,
+            r.duration_ms as f64 / 1000.0
+        ));
+    }
+
+    let resources: Vec<_> = summary
+        .results
+        .iter()
+        .flat_map(|r| r.resources.iter().map(move |rc| (r, rc)))
+        .collect();
+    if !resources.is_empty() {
+        out.push_str("\n#### Changed resources\n\n");
+        out.push_str("| Workspace | Address | Action | Attributes |\n");
+        out.push_str("|---|---|---|---|\n");
+        for (r, rc) in resources {
+            let attributes: Vec<String> = rc
+                .attributes
+                .iter()
+                .map(|a| format!("`{}`: {} → {}", a.path, a.old, a.new))
+                .collect();
+            out.push_str(&format!(
+                "| {} | `{}` | {} | {} |\n",
+                md_cell(&r.workspace),
+                rc.address,
+                rc.action.as_str(),
+                md_cell(&attributes.join("\n"))
+            ));
+        }
+    }
+
+    let failures: Vec<_> = summary
+        .results
+        .iter()
+        .filter_map(|r| Some((r, r.error.as_deref()?)))
+        .collect();
+    if !failures.is_empty() {
+        out.push_str("\n#### Errors\n\n");
+        for (r, error) in failures {
+            out.push_str(&format!("- **{}**: {}\n", r.workspace, md_cell(error)));
+        }
+    }
+    out
+}
+
+/// Quote a CSV field when it needs it (RFC 4180).
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+fn render_csv(summary: &RunSummary) -> String {
+    let mut out = String::from(
+        "profile,workspace,status,engine,terraform_version,drift,scan_mode,changed_resources,create,update,delete,replace,duration_ms,error\n",
+    );
+    for r in &summary.results {
+        let fields = [
+            summary.profile.clone(),
+            r.workspace.clone(),
+            r.status.as_str().to_string(),
+            r.engine.to_string(),
+            r.terraform_version.clone(),
+            r.drift.to_string(),
+            r.scan_mode.as_str().to_string(),
+            r.changed_resources.to_string(),
+            r.counts.create.to_string(),
+            r.counts.update.to_string(),
+            r.counts.delete.to_string(),
+            r.counts.replace.to_string(),
+            r.duration_ms.to_string(),
+            r.error.clone().unwrap_or_default(),
+        ];
+        let row: Vec<String> = fields.iter().map(|f| csv_field(f)).collect();
+        out.push_str(&row.join(","));
+        out.push('\n');
+    }
+    out
+}
+
+/// Escape text for XML content and attribute values, dropping characters XML
+/// 1.0 cannot carry.
+fn xml_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            '\t' | '\n' | '\r' => out.push(c),
+            c if (c as u32) < 0x20 => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn seconds(ms: u128) -> String {
+    format!("{:.3}", ms as f64 / 1000.0)
+}
+
+/// JUnit XML for CI test dashboards: drift is a failure listing the changed
+/// resources, errors and timeouts are errors.
+fn render_junit(summary: &RunSummary) -> String {
+    let results = &summary.results;
+    let failures = results
+        .iter()
+        .filter(|r| r.status == WorkspaceStatus::Drift)
+        .count();
+    let errors = results
+        .iter()
+        .filter(|r| matches!(r.status, WorkspaceStatus::Error | WorkspaceStatus::Timeout))
+        .count();
+    let time = seconds(results.iter().map(|r| r.duration_ms).sum());
+    let profile = xml_escape(&summary.profile);
+
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str(&format!(
+        "<testsuites name=\"terradrift\" tests=\"{}\" failures=\"{failures}\" errors=\"{errors}\" time=\"{time}\">\n",
+        results.len()
+    ));
+    out.push_str(&format!(
+        "  <testsuite name=\"{profile}\" tests=\"{}\" failures=\"{failures}\" errors=\"{errors}\" skipped=\"0\" time=\"{time}\">\n",
+        results.len()
+    ));
+    for r in results {
+        out.push_str(&format!(
+            "    <testcase classname=\"terradrift.{profile}\" name=\"{}\" time=\"{}\"",
+            xml_escape(&r.workspace),
+            seconds(r.duration_ms)
+        ));
+        match r.status {
+            WorkspaceStatus::Clean => {
+                out.push_str("/>\n");
+                continue;
+            }
+            WorkspaceStatus::Drift => {
+                let body: Vec<String> = r
+                    .resources
+                    .iter()
+                    .map(|rc| format!("{} ({})", rc.address, rc.action.as_str()))
+                    .collect();
+                out.push_str(&format!(
+                    ">\n      <failure type=\"drift\" message=\"{} changed resource(s)\">{}</failure>\n",
+                    changed_label(r),
+                    xml_escape(&body.join("\n"))
+                ));
+            }
+            WorkspaceStatus::Error | WorkspaceStatus::Timeout => {
+                let message = match (&r.error, r.status) {
+                    (Some(error), _) => error.clone(),
+                    (None, WorkspaceStatus::Timeout) => "plan timed out".to_string(),
+                    (None, _) => "scan failed".to_string(),
+                };
+                let body: Vec<String> = r
+                    .diagnostics
+                    .iter()
+                    .map(|d| format!("{}: {}", d.severity, d.summary))
+                    .collect();
+                out.push_str(&format!(
+                    ">\n      <error type=\"{}\" message=\"{}\">{}</error>\n",
+                    r.status.as_str(),
+                    xml_escape(&message),
+                    xml_escape(&body.join("\n"))
+                ));
+            }
+        }
+        if let Some(stderr) = &r.stderr {
+            out.push_str(&format!(
+                "      <system-err>{}</system-err>\n",
+                xml_escape(stderr)
+            ));
+        }
+        out.push_str("    </testcase>\n");
+    }
+    out.push_str("  </testsuite>\n</testsuites>\n");
+    out
+}
+
+/// SARIF rules, one per plan action.
+const SARIF_RULES: &[(ChangeAction, &str, &str)] = &[
+    (
+        ChangeAction::Update,
+        "warning",
+        "Resource differs from its configuration and would be updated in place",
+    ),
+    (
+        ChangeAction::Replace,
+        "error",
+        "Resource differs from its configuration and would be destroyed and recreated",
+    ),
+    (
+        ChangeAction::Delete,
+        "error",
+        "Resource is no longer in the configuration and would be destroyed",
+    ),
+    (
+        ChangeAction::Create,
+        "warning",
+        "Resource is missing and would be created",
+    ),
+    (
+        ChangeAction::Read,
+        "note",
+        "Data source would be read during apply",
+    ),
+    (
+        ChangeAction::Forget,
+        "note",
+        "Resource would be removed from state without being destroyed",
+    ),
+];
+
+fn sarif_rule_id(action: ChangeAction) -> String {
+    format!("drift/{}", action.as_str())
+}
+
+/// SARIF 2.1.0 log for code scanning. Each drifted resource is a result
+/// located at its workspace's configuration directory (or state when plans
+/// run in the cwd), fingerprinted so repeated scans update the same alert.
+fn sarif(summary: &RunSummary) -> Value {
+    let rules: Vec<Value> = SARIF_RULES
+        .iter()
+        .map(|(action, level, description)| {
+            json!({
+                "id": sarif_rule_id(*action),
+                "name": format!("Drift{}", capitalize(action.as_str())),
+                "shortDescription": { "text": format!("Drift: {}", action.as_str()) },
+                "fullDescription": { "text": description },
+                "defaultConfiguration": { "level": level },
+                "properties": { "tags": ["drift", "terraform"] },
+            })
+        })
+        .collect();
+
+    let results: Vec<Value> = summary
+        .results
+        .iter()
+        .filter(|r| r.status == WorkspaceStatus::Drift)
+        .flat_map(|r| {
+            r.resources
+                .iter()
+                .map(move |rc| sarif_result(summary, r, rc))
+        })
+        .collect();
+
+    let mut run = json!({
+        "tool": {
+            "driver": {
+                "name": "terradrift",
+                "version": env!("CARGO_PKG_VERSION"),
+                "informationUri": "https://github.com/mrdorianh/TerraDiff",
+                "rules": rules,
+            }
+        },
+        "automationDetails": { "id": format!("terradrift/{}/", summary.profile) },
+        "results": results,
+    });
+    if let Some(base) = source_root(summary) {
+        run["originalUriBaseIds"] = json!({ SARIF_SRCROOT: { "uri": file_uri(&base) } });
+    }
+    json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [run],
+    })
+}
+
+fn sarif_result(summary: &RunSummary, r: &WorkspaceResult, rc: &ResourceChange) -> Value {
+    let rule_index = SARIF_RULES
+        .iter()
+        .position(|(action, _, _)| *action == rc.action)
+        .unwrap_or_default();
+    let mut message = format!(
+        "{} in workspace {} would be {}",
+        rc.address,
+        r.workspace,
+        past_tense(rc.action)
+    );
+    if let Some(reason) = &rc.action_reason {
+        message.push_str(&format!(" ({reason})"));
+    }
+    let changed: Vec<&str> = rc.attributes.iter().map(|a| a.path.as_str()).collect();
+    if !changed.is_empty() {
+        message.push_str(&format!("; changed: {}", changed.join(", ")));
+    }
+
+    // Code scanning drops results without a physical location; plans in the
+    // cwd have no configuration file, but the state key names the workspace.
+    let artifact = match source_path(summary, r) {
+        Some(path) => json!({ "uri": path, "uriBaseId": SARIF_SRCROOT }),
+        None => json!({ "uri": r.state }),
+    };
+    let location = json!({
+        "physicalLocation": {
+            "artifactLocation": artifact,
+            "region": { "startLine": 1 },
+        },
+        "logicalLocations": [{
+            "fullyQualifiedName": format!("{}/{}", r.workspace, rc.address),
+            "name": rc.address,
+            "kind": "resource",
+        }],
+    });
+
+    json!({
+        "ruleId": sarif_rule_id(rc.action),
+        "ruleIndex": rule_index,
+        "level": SARIF_RULES[rule_index].1,
+        "message": { "text": message },
+        "locations": [location],
+        "partialFingerprints": {
+            "terradriftResource/v1": fingerprint(summary, r, rc),
+        },
+        "properties": {
+            "profile": summary.profile,
+            "workspace": r.workspace,
+            "state": r.state,
+            "resourceType": rc.resource_type,
+            "provider": rc.provider,
+        },
+    })
+}
+
+/// `uriBaseId` SARIF locations are relative to.
+const SARIF_SRCROOT: &str = "%SRCROOT%";
+
+/// Directory report paths are relative to: the working directory when
+/// `root_dir` lies inside it (a CI job runs in the repository checkout), else
+/// `root_dir` itself.
+fn source_root(summary: &RunSummary) -> Option<PathBuf> {
+    let root = summary.root_dir.as_ref()?;
+    match std::env::current_dir().and_then(|d| d.canonicalize()) {
+        Ok(cwd) if root.starts_with(&cwd) => Some(cwd),
+        _ => Some(root.clone()),
+    }
+}
+
+/// `/`-separated path of the workspace's `config_file` under [`source_root`].
+fn source_path(summary: &RunSummary, r: &WorkspaceResult) -> Option<String> {
+    let file = summary.root_dir.as_ref()?.join(r.config_file.as_ref()?);
+    let rel = file.strip_prefix(source_root(summary)?).ok()?;
+    let parts: Vec<_> = rel
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect();
+    Some(parts.join("/"))
+}
+
+/// `file://` URI of a directory, with a trailing slash as SARIF base URIs need.
+fn file_uri(dir: &Path) -> String {
+    let path: String = dir
+        .to_string_lossy()
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                (b as char).to_string()
+            }
+            b => format!("%{b:02X}"),
+        })
+        .collect();
+    format!("file://{}/", path.trim_end_matches('/'))
+}
+
+/// Stable identity of a drifted resource across runs.
+fn fingerprint(summary: &RunSummary, r: &WorkspaceResult, rc: &ResourceChange) -> String {
+    let digest =
+        Sha256::digest(format!("{}/{}/{}", summary.profile, r.workspace, rc.address).as_bytes());
+    hex(&digest)
+}
+
+/// GitLab Code Quality report: the merge request widget shows one issue per
+/// drifted resource.
+fn gitlab_codequality(summary: &RunSummary) -> Value {
+    let issues: Vec<Value> = summary
+        .results
+        .iter()
+        .filter(|r| r.status == WorkspaceStatus::Drift)
+        .flat_map(|r| {
+            r.resources.iter().map(move |rc| {
+                let severity = match rc.action {
+                    ChangeAction::Delete | ChangeAction::Replace => "critical",
+                    ChangeAction::Update => "major",
+                    ChangeAction::Create => "minor",
+                    ChangeAction::Read | ChangeAction::Forget => "info",
+                };
+                json!({
+                    "type": "issue",
+                    "check_name": sarif_rule_id(rc.action),
+                    "description": format!(
+                        "Drift in {}/{}: {} would be {}",
+                        summary.profile,
+                        r.workspace,
+                        rc.address,
+                        past_tense(rc.action)
+                    ),
+                    "categories": ["Bug Risk"],
+                    "fingerprint": fingerprint(summary, r, rc),
+                    "severity": severity,
+                    // Plans in the cwd have no file; the state at least
+                    // names the workspace.
+                    "location": {
+                        "path": source_path(summary, r).unwrap_or_else(|| r.state.clone()),
+                        "lines": { "begin": 1 },
+                    },
+                })
+            })
+        })
+        .collect();
+    Value::Array(issues)
+}
+
+fn capitalize(s: &str) -> String {
+    let mut chars = s.chars();
+    chars
+        .next()
+        .map(|c| c.to_ascii_uppercase().to_string() + chars.as_str())
+        .unwrap_or_default()
+}
+
+fn past_tense(action: ChangeAction) -> &'static str {
+    match action {
+        ChangeAction::Create => "created",
+        ChangeAction::Read => "read",
+        ChangeAction::Update => "updated",
+        ChangeAction::Delete => "destroyed",
+        ChangeAction::Replace => "replaced",
+        ChangeAction::Forget => "forgotten",
+    }
+}
+
+pub(crate) fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plan::{ChangeAction, ChangeCounts, DriftCategory, PendingChanges, ResourceChange};
+
+    fn summary() -> RunSummary {
+        let result = |workspace: &str, status| WorkspaceResult {
+            state: format!("s3://tfstate/{workspace}.tfstate"),
+            scan_mode: ScanMode::Full,
+            ..WorkspaceResult::fixture(workspace, status)
+        };
+        let mut drift = result("app", WorkspaceStatus::Drift);
+        drift.config_dir = Some("/work/infra/envs/app".into());
+        drift.config_file = Some("envs/app/main.tf".into());
+        drift.counts.update = 1;
+        drift.resources.push(ResourceChange::fixture(
+            "aws_instance.web",
+            ChangeAction::Update,
+        ));
+        let mut failed = result("net", WorkspaceStatus::Error);
+        failed.error = Some("terraform init failed: \"exit 1\", retry".into());
+        RunSummary {
+            profile: "prod".into(),
+            report_url: None,
+            root_dir: Some("/work/infra".into()),
+            results: vec![drift, failed],
+        }
+    }
+
+    #[test]
+    fn renders_csv_with_quoting() {
+        let csv = summary().render(OutputFormat::Csv, false).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(
+            lines[1],
+            "prod,app,drift,terraform,1.7.5,true,full,1,0,1,0,0,1500,"
+        );
+        assert!(lines[2].ends_with(",\"terraform init failed: \"\"exit 1\"\", retry\""));
+    }
+
+    #[test]
+    fn renders_ndjson_and_markdown() {
+        let s = summary();
+        let ndjson = s.render(OutputFormat::Ndjson, false).unwrap();
+        let first: serde_json::Value =
+            serde_json::from_str(ndjson.lines().next().unwrap()).unwrap();
+        assert_eq!(first["profile"], "prod");
+        assert_eq!(first["workspace"], "app");
+        assert_eq!(ndjson.lines().count(), 2);
+
+        let md = s.render(OutputFormat::Markdown, false).unwrap();
+        assert!(md.contains("| app | 🚨 drift | 1 (+0 ~1 -0 ±0) | terraform 1.7.5 | 1.5s |"));
+        assert!(md.contains("| app | `aws_instance.web` | update |  |"));
+        assert!(md.contains("- **net**: terraform init failed"));
+    }
+
+    #[test]
+    fn renders_sarif() {
+        let sarif: Value =
+            serde_json::from_str(&summary().render(OutputFormat::Sarif, false).unwrap()).unwrap();
+        let run = &sarif["runs"][0];
+        assert_eq!(sarif["version"], "2.1.0");
+        assert_eq!(run["tool"]["driver"]["rules"][0]["id"], "drift/update");
+        let results = run["results"].as_array().unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0]["ruleId"], "drift/update");
+        assert_eq!(results[0]["level"], "warning");
+        let artifact = &results[0]["locations"][0]["physicalLocation"]["artifactLocation"];
+        assert_eq!(artifact["uri"], "envs/app/main.tf");
+        assert_eq!(artifact["uriBaseId"], "%SRCROOT%");
+        assert_eq!(
+            run["originalUriBaseIds"]["%SRCROOT%"]["uri"],
+            "file:///work/infra/"
+        );
+        assert_eq!(
+            results[0]["message"]["text"],
+            "aws_instance.web in workspace app would be updated"
+        );
+        assert_eq!(
+            results[0]["partialFingerprints"]["terradriftResource/v1"]
+                .as_str()
+                .unwrap()
+                .len(),
+            64
+        );
+    }
+
+    #[test]
+    fn sarif_falls_back_to_state_key_without_root_dir() {
+        let mut s = summary();
+        s.root_dir = None;
+        let sarif: Value =
+            serde_json::from_str(&s.render(OutputFormat::Sarif, false).unwrap()).unwrap();
+        let run = &sarif["runs"][0];
+        assert!(run.get("originalUriBaseIds").is_none());
+        let artifact = &run["results"][0]["locations"][0]["physicalLocation"]["artifactLocation"];
+        assert_eq!(artifact["uri"], "s3://tfstate/app.tfstate");
+        assert!(artifact.get("uriBaseId").is_none());
+    }
+
+    #[test]
+    fn renders_gitlab_codequality() {
+        let report: Value = serde_json::from_str(
+            &summary()
+                .render(OutputFormat::GitlabCodequality, false)
+                .unwrap(),
+        )
+        .unwrap();
+        let issues = report.as_array().unwrap();
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0]["check_name"], "drift/update");
+        assert_eq!(issues[0]["severity"], "major");
+        assert_eq!(issues[0]["location"]["path"], "envs/app/main.tf");
+        assert_eq!(
+            issues[0]["description"],
+            "Drift in prod/app: aws_instance.web would be updated"
+        );
+
+        // Same resource, same fingerprint as the SARIF result.
+        let sarif: Value =
+            serde_json::from_str(&summary().render(OutputFormat::Sarif, false).unwrap()).unwrap();
+        assert_eq!(
+            issues[0]["fingerprint"],
+            sarif["runs"][0]["results"][0]["partialFingerprints"]["terradriftResource/v1"]
+        );
+    }
+
+    #[test]
+    fn pending_changes_raise_no_findings() {
+        let pending = || {
+            let mut rc = ResourceChange::fixture("aws_sqs_queue.jobs", ChangeAction::Delete);
+            rc.category = Some(DriftCategory::PendingConfigChange);
+            Some(PendingChanges {
+                counts: ChangeCounts::from_resources(std::slice::from_ref(&rc)),
+                resources: vec![rc],
+            })
+        };
+        let mut s = summary();
+        s.results[0].pending = pending();
+        let mut queue = WorkspaceResult::fixture("queue", WorkspaceStatus::Clean);
+        queue.pending = pending();
+        s.results.push(queue);
+
+        let sarif: Value =
+            serde_json::from_str(&s.render(OutputFormat::Sarif, false).unwrap()).unwrap();
+        assert_eq!(sarif["runs"][0]["results"].as_array().unwrap().len(), 1);
+        let issues: Value =
+            serde_json::from_str(&s.render(OutputFormat::GitlabCodequality, false).unwrap())
+                .unwrap();
+        assert_eq!(issues.as_array().unwrap().len(), 1);
+        for format in [OutputFormat::Junit, OutputFormat::Markdown] {
+            assert!(!s.render(format, false).unwrap().contains("aws_sqs_queue"));
+        }
+
+        let json: Value =
+            serde_json::from_str(&s.render(OutputFormat::Json, false).unwrap()).unwrap();
+        let queue = &json["results"][2];
+        assert_eq!(queue["changed_resources"], 0);
+        assert_eq!(queue["pending"]["delete"], 1);
+        assert_eq!(
+            queue["pending"]["resources"][0]["category"],
+            "pending_config_change"
+        );
+        let html = s.render(OutputFormat::Html, false).unwrap();
+        assert!(html.contains("<code>aws_sqs_queue.jobs</code>"));
+    }
+
+    #[test]
+    fn renders_html() {
+        let html = summary().render(OutputFormat::Html, false).unwrap();
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(!html.contains("<link") && !html.contains("src=\""));
+        assert!(html.contains("<table id=\"workspaces\" class=\"sortable\">"));
+        assert!(html.contains("<a href=\"#ws-app\">app</a>"));
+        assert!(html
+            .contains("<code>aws_instance.web</code> <span class=\"action update\">update</span>"));
+        assert!(html.contains("terraform init failed: &quot;exit 1&quot;, retry"));
+        assert!(html.contains("2 (1 drift, 1 error, 0 timeout, 0 clean)"));
+    }
+
+    #[test]
+    fn renders_junit() {
+        let xml = summary().render(OutputFormat::Junit, false).unwrap();
+        assert!(xml.contains(
+            "<testsuite name=\"prod\" tests=\"2\" failures=\"1\" errors=\"1\" skipped=\"0\" time=\"3.000\">"
+        ));
+        assert!(xml.contains(
+            "<testcase classname=\"terradrift.prod\" name=\"app\" time=\"1.500\">\n      <failure type=\"drift\" message=\"1 (+0 ~1 -0 ±0) changed resource(s)\">aws_instance.web (update)</failure>"
+        ));
+        assert!(xml.contains(
+            "<error type=\"error\" message=\"terraform init failed: &quot;exit 1&quot;, retry\">"
+        ));
+    }
+}
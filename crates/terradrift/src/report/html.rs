@@ -0,0 +1,320 @@
+//! Self-contained HTML report: one file with inline CSS and JS, no external
+//! assets, so it can be published as a CI artifact as-is.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::{changed_label, status_icon, xml_escape as escape, RunSummary};
+use crate::orchestrator::{WorkspaceResult, WorkspaceStatus};
+use crate::plan::ResourceChange;
+
+const STYLE: &str = r#"
+body { font: 14px/1.45 system-ui, sans-serif; margin: 2rem auto; max-width: 72rem; padding: 0 1rem; color: #1f2328; }
+h1 { font-size: 1.5rem; margin-bottom: .5rem; }
+h2 { font-size: 1.15rem; margin: 2rem 0 .5rem; }
+code, pre { font: 12px/1.4 ui-monospace, SFMono-Regular, Menlo, monospace; }
+pre { background: #f6f8fa; padding: .5rem; overflow-x: auto; white-space: pre-wrap; }
+dl.meta { display: grid; grid-template-columns: max-content 1fr; gap: .15rem 1rem; margin: 0 0 1.5rem; }
+dl.meta dt { color: #59636e; }
+dl.meta dd { margin: 0; }
+table { border-collapse: collapse; width: 100%; }
+th, td { border-bottom: 1px solid #d1d9e0; padding: .35rem .6rem; text-align: left; vertical-align: top; }
+td.num { text-align: right; }
+table.sortable th { cursor: pointer; user-select: none; white-space: nowrap; }
+table.sortable th[aria-sort=ascending]::after { content: " ▲"; }
+table.sortable th[aria-sort=descending]::after { content: " ▼"; }
+tr.drift { background: #fff8c5; }
+tr.error, tr.timeout { background: #ffebe9; }
+.muted { color: #59636e; }
+details.resource { border: 1px solid #d1d9e0; border-radius: 6px; margin: .35rem 0; }
+details.resource > summary { cursor: pointer; padding: .35rem .6rem; }
+details.resource > table { margin: 0 0 .35rem; }
+.action { border-radius: 1em; padding: 0 .5em; font-size: 12px; background: #ddf4ff; }
+.action.delete, .action.replace { background: #ffebe9; }
+.action.create { background: #dafbe1; }
+.old { color: #cf222e; }
+.new { color: #1a7f37; }
+.toolbar { margin: 1rem 0; }
+"#;
+
+const SCRIPT: &str = r#"
+document.querySelectorAll("table.sortable th").forEach((th, col) => {
+  th.addEventListener("click", () => {
+    const table = th.closest("table");
+    const asc = th.getAttribute("aria-sort") !== "ascending";
+    table.querySelectorAll("th").forEach(h => h.removeAttribute("aria-sort"));
+    th.setAttribute("aria-sort", asc ? "ascending" : "descending");
+    const key = row => {
+      const cell = row.cells[col];
+      return cell.dataset.sort ?? cell.textContent.trim();
+    };
+    const numeric = th.dataset.type === "number";
+    const body = table.tBodies[0];
+    [...body.rows]
+      .sort((a, b) => {
+        const d = numeric ? key(a) - key(b) : key(a).localeCompare(key(b));
+        return asc ? d : -d;
+      })
+      .forEach(row => body.appendChild(row));
+  });
+});
+document.querySelectorAll("button[data-expand]").forEach(button => {
+  button.addEventListener("click", () => {
+    const open = button.dataset.expand === "true";
+    document.querySelectorAll("details").forEach(d => (d.open = open));
+  });
+});
+"#;
+
+/// Render `summary` as a single HTML page: run metadata, a sortable workspace
+/// table and, per workspace, expandable resource diffs and error details.
+pub(super) fn render_html(summary: &RunSummary) -> String {
+    let title = format!("Terradrift · {}", escape(&summary.profile));
+    let mut out = format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+         <title>{title}</title>\n<style>{STYLE}</style>\n</head>\n<body>\n"
+    );
+    out.push_str(&format!(
+        "<h1>Terradrift · profile <code>{}</code></h1>\n",
+        escape(&summary.profile)
+    ));
+    out.push_str(&metadata(summary));
+    out.push_str(&workspace_table(summary));
+
+    let details: Vec<&WorkspaceResult> = summary
+        .results
+        .iter()
+        .filter(|r| r.status != WorkspaceStatus::Clean)
+        .collect();
+    if !details.is_empty() {
+        out.push_str(
+            "<div class=\"toolbar\"><button data-expand=\"true\">Expand all</button> \
+             <button data-expand=\"false\">Collapse all</button></div>\n",
+        );
+    }
+    for r in details {
+        out.push_str(&workspace_section(r));
+    }
+    out.push_str(&format!("<script>{SCRIPT}</script>\n</body>\n</html>\n"));
+    out
+}
+
+fn metadata(summary: &RunSummary) -> String {
+    let count = |status| {
+        summary
+            .results
+            .iter()
+            .filter(|r| r.status == status)
+            .count()
+    };
+    let plan_ms: u128 = summary.results.iter().map(|r| r.duration_ms).sum();
+    let rows = [
+        ("Generated", utc_now()),
+        ("Terradrift", env!("CARGO_PKG_VERSION").to_string()),
+        (
+            "Workspaces",
+            format!(
+                "{} ({} drift, {} error, {} timeout, {} clean)",
+                summary.results.len(),
+                count(WorkspaceStatus::Drift),
+                count(WorkspaceStatus::Error),
+                count(WorkspaceStatus::Timeout),
+                count(WorkspaceStatus::Clean)
+            ),
+        ),
+        ("Plan time", format!("{:.1}s", plan_ms as f64 / 1000.0)),
+    ];
+    let mut out = String::from("<dl class=\"meta\">\n");
+    for (name, value) in rows {
+        out.push_str(&format!("<dt>{name}</dt><dd>{}</dd>\n", escape(&value)));
+    }
+    out.push_str("</dl>\n");
+    out
+}
+
+fn workspace_table(summary: &RunSummary) -> String {
+    let mut out = String::from(
+        "<table id=\"workspaces\" class=\"sortable\">\n<thead><tr>\
+         <th>Workspace</th><th data-type=\"number\">Status</th>\
+         <th data-type=\"number\">Changed</th><th>Engine</th>\
+         <th data-type=\"number\">Duration</th><th>State</th></tr></thead>\n<tbody>\n",
+    );
+    for r in &summary.results {
+        let workspace = if r.status == WorkspaceStatus::Clean {
+            escape(&r.workspace)
+        } else {
+            format!(
+                "<a href=\"#{}\">{}</a>",
+                anchor(&r.workspace),
+                escape(&r.workspace)
+            )
+        };
+        // Problems first when sorting by status ascending.
+        let rank = match r.status {
+            WorkspaceStatus::Error => 0,
+            WorkspaceStatus::Timeout => 1,
+            WorkspaceStatus::Drift => 2,
+            WorkspaceStatus::Clean => 3,
+        };
+        out.push_str(&format!(
+            "<tr class=\"{status}\"><td>{workspace}</td><td data-sort=\"{rank}\">{} {status}</td>\
+             <td class=\"num\" data-sort=\"{}\">{}</td><td>{} {}</td>\
+             <td class=\"num\" data-sort=\"{}\">{:.1}s</td><td><code>{}</code></td></tr>\n",
+            status_icon(r.status),
+            r.changed_resources,
+            escape(&changed_label(r)),
+            r.engine,
+            escape(&r.terraform_version),
+            r.duration_ms,
+            r.duration_ms as f64 / 1000.0,
+            escape(&r.state),
+            status = r.status.as_str(),
+        ));
+    }
+    out.push_str("</tbody>\n</table>\n");
+    out
+}
+
+fn workspace_section(r: &WorkspaceResult) -> String {
+    let mut out = format!(
+        "<section id=\"{}\">\n<h2>{} {}</h2>\n<p class=\"muted\">State <code>{}</code>",
+        anchor(&r.workspace),
+        status_icon(r.status),
+        escape(&r.workspace),
+        escape(&r.state)
+    );
+    if let Some(dir) = &r.config_dir {
+        out.push_str(&format!(
+            " · configuration <code>{}</code>",
+            escape(&dir.display().to_string())
+        ));
+    }
+    out.push_str("</p>\n");
+
+    if let Some(error) = &r.error {
+        out.push_str(&format!("<pre class=\"old\">{}</pre>\n", escape(error)));
+    }
+    for d in &r.diagnostics {
+        out.push_str(&format!(
+            "<p><strong>{}</strong>: {}",
+            escape(&d.severity),
+            escape(&d.summary)
+        ));
+        if let Some(address) = &d.address {
+            out.push_str(&format!(
+                " <span class=\"muted\">at <code>{}</code></span>",
+                escape(address)
+            ));
+        }
+        out.push_str("</p>\n");
+        if !d.detail.is_empty() {
+            out.push_str(&format!("<pre>{}</pre>\n", escape(&d.detail)));
+        }
+    }
+    if let Some(stderr) = &r.stderr {
+        out.push_str(&format!(
+            "<details><summary>stderr</summary><pre>{}</pre></details>\n",
+            escape(stderr)
+        ));
+    }
+    for rc in &r.resources {
+        out.push_str(&resource_details(rc));
+    }
+    for rc in r.pending.iter().flat_map(|p| &p.resources) {
+        out.push_str(&resource_details(rc));
+    }
+    out.push_str("</section>\n");
+    out
+}
+
+fn resource_details(rc: &ResourceChange) -> String {
+    let action = rc.action.as_str();
+    let mut out = format!(
+        "<details class=\"resource\"><summary><code>{}</code> <span class=\"action {action}\">{action}</span> \
+         <span class=\"muted\">{} · {}",
+        escape(&rc.address),
+        escape(&rc.resource_type),
+        escape(&rc.provider)
+    );
+    if let Some(category) = rc.category {
+        out.push_str(&format!(" · {}", category.label()));
+    }
+    if let Some(reason) = &rc.action_reason {
+        out.push_str(&format!(" · {}", escape(reason)));
+    }
+    out.push_str("</span></summary>\n");
+    if rc.attributes.is_empty() {
+        out.push_str("<p class=\"muted\">No attribute changes recorded.</p>\n");
+    } else {
+        out.push_str("<table><tr><th>Attribute</th><th>Before</th><th>After</th></tr>\n");
+        for a in &rc.attributes {
+            out.push_str(&format!(
+                "<tr><td><code>{}</code></td><td class=\"old\"><code>{}</code></td>\
+                 <td class=\"new\"><code>{}</code></td></tr>\n",
+                escape(&a.path),
+                escape(&a.old.to_string()),
+                escape(&a.new.to_string())
+            ));
+        }
+        out.push_str("</table>\n");
+    }
+    out.push_str("</details>\n");
+    out
+}
+
+/// Fragment id of a workspace's section.
+fn anchor(workspace: &str) -> String {
+    let id: String = workspace
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("ws-{id}")
+}
+
+/// Current time as RFC 3339 UTC, e.g. `2024-05-01T12:00:00Z`.
+fn utc_now() -> String {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let (days, rem) = (secs / 86_400, secs % 86_400);
+    let (year, month, day) = civil_from_days(days as i64);
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
+
+/// Gregorian date of a day count since 1970-01-01 (Howard Hinnant's algorithm).
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_days_to_dates() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(19_723), (2024, 1, 1));
+        assert_eq!(civil_from_days(19_782), (2024, 2, 29));
+    }
+}
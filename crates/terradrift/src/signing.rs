@@ -0,0 +1,138 @@
+use std::io::Cursor;
+
+use anyhow::{Context, Result};
+use pgp::types::PublicKeyTrait;
+use pgp::{Deserializable, SignedPublicKey, StandaloneSignature};
+
+/// A set of OpenPGP public keys trusted to sign release checksum files.
+pub struct Keyring {
+    keys: Vec<SignedPublicKey>,
+}
+
+impl Keyring {
+    /// Parse one or more public keys, ASCII-armored or binary.
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        let keys = if is_armored(data) {
+            let (keys, _) = SignedPublicKey::from_armor_many(Cursor::new(data))
+                .context("Parsing armored OpenPGP keyring")?;
+            keys.collect::<pgp::errors::Result<Vec<_>>>()
+        } else {
+            SignedPublicKey::from_bytes_many(Cursor::new(data))
+                .collect::<pgp::errors::Result<Vec<_>>>()
+        }
+        .context("Parsing OpenPGP keyring")?;
+
+        for key in &keys {
+            key.verify().with_context(|| {
+                format!(
+                    "Invalid self-signature on key {}",
+                    fingerprint_hex(&key.primary_key)
+                )
+            })?;
+        }
+        if keys.is_empty() {
+            anyhow::bail!("OpenPGP keyring contains no public keys");
+        }
+        Ok(Keyring { keys })
+    }
+
+    /// Keep only the key whose primary fingerprint is `fingerprint`, failing if
+    /// it is absent.
+    pub fn pin(self, fingerprint: &str) -> Result<Self> {
+        let wanted = fingerprint.replace(' ', "").to_ascii_uppercase();
+        let keys: Vec<_> = self
+            .keys
+            .into_iter()
+            .filter(|k| fingerprint_hex(&k.primary_key) == wanted)
+            .collect();
+        if keys.is_empty() {
+            anyhow::bail!("OpenPGP keyring does not contain the pinned key {wanted}");
+        }
+        Ok(Keyring { keys })
+    }
+
+    /// Verify a detached signature (binary or armored) over `data` against any
+    /// primary key or signing subkey in the keyring.
+    pub fn verify_detached(&self, data: &[u8], signature: &[u8]) -> Result<()> {
+        let sig = if is_armored(signature) {
+            StandaloneSignature::from_armor_single(Cursor::new(signature)).map(|(sig, _)| sig)
+        } else {
+            StandaloneSignature::from_bytes(Cursor::new(signature))
+        }
+        .context("Parsing OpenPGP signature")?;
+
+        for key in &self.keys {
+            if sig.verify(&key.primary_key, data).is_ok() {
+                return Ok(());
+            }
+            if key
+                .public_subkeys
+                .iter()
+                .any(|sub| sig.verify(&sub.key, data).is_ok())
+            {
+                return Ok(());
+            }
+        }
+        let trusted: Vec<_> = self
+            .keys
+            .iter()
+            .map(|k| fingerprint_hex(&k.primary_key))
+            .collect();
+        anyhow::bail!(
+            "OpenPGP signature was not made by a trusted key (trusted: {})",
+            trusted.join(", ")
+        )
+    }
+}
+
+fn is_armored(data: &[u8]) -> bool {
+    data.trim_ascii_start().starts_with(b"-----BEGIN PGP")
+}
+
+fn fingerprint_hex(key: &impl PublicKeyTrait) -> String {
+    key.fingerprint()
+        .as_bytes()
+        .iter()
+        .map(|b| format!("{b:02X}"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RELEASE_KEY: &[u8] = include_bytes!("../tests/fixtures/release/release-key.asc");
+    const OTHER_KEY: &[u8] = include_bytes!("../tests/fixtures/release/other-key.asc");
+    const SUMS: &[u8] = include_bytes!("../tests/fixtures/release/terraform_1.7.5_SHA256SUMS");
+    const SUMS_SIG: &[u8] =
+        include_bytes!("../tests/fixtures/release/terraform_1.7.5_SHA256SUMS.sig");
+    const RELEASE_FINGERPRINT: &str = "2BF2 49E1 6D16 0AA5 4F17 42E7 3E13 4FF9 EFC5 F5E7";
+
+    #[test]
+    fn verifies_signature_from_trusted_key() {
+        let keyring = Keyring::from_bytes(RELEASE_KEY)
+            .unwrap()
+            .pin(RELEASE_FINGERPRINT)
+            .unwrap();
+        keyring.verify_detached(SUMS, SUMS_SIG).unwrap();
+    }
+
+    #[test]
+    fn rejects_tampered_checksums() {
+        let keyring = Keyring::from_bytes(RELEASE_KEY).unwrap();
+        let mut tampered = SUMS.to_vec();
+        tampered[0] = if tampered[0] == b'0' { b'1' } else { b'0' };
+        assert!(keyring.verify_detached(&tampered, SUMS_SIG).is_err());
+    }
+
+    #[test]
+    fn rejects_signature_from_untrusted_key() {
+        let keyring = Keyring::from_bytes(OTHER_KEY).unwrap();
+        let err = keyring.verify_detached(SUMS, SUMS_SIG).unwrap_err();
+        assert!(err.to_string().contains("not made by a trusted key"));
+        assert!(Keyring::from_bytes(OTHER_KEY)
+            .unwrap()
+            .pin(RELEASE_FINGERPRINT)
+            .is_err());
+    }
+}
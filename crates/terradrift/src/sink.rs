@@ -1,15 +1,1186 @@
+//! Notification sinks: where a run's summary is sent once all workspaces are
+//! scanned.
+
 use anyhow::{Context, Result};
-use serde_json;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::config::{resolve_secret, NotifyWhen, Severity, SeverityMap, SinkConfig, SinkKind};
+use crate::metrics::write_atomic;
+use crate::orchestrator::{WorkspaceResult, WorkspaceStatus};
+use crate::plan::ChangeAction;
+use crate::report::{changed_label, hex, status_icon, OutputFormat, RunSummary};
+
+#[async_trait]
+pub trait Sink: Send + Sync {
+    /// Short name for log messages, e.g. `slack`.
+    fn name(&self) -> &'static str;
+
+    /// Deliver the run's summary.
+    async fn send(&self, summary: &RunSummary) -> Result<()>;
+}
+
+/// Time allowed to connect to a sink's endpoint.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Time allowed for one request to a sink, response included, so a hung
+/// endpoint cannot hold up the run.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// HTTP client the sinks of a run share.
+pub fn http_client() -> Result<reqwest::Client> {
+    reqwest::Client::builder()
+        .connect_timeout(CONNECT_TIMEOUT)
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .context("Building HTTP client")
+}
+
+/// Build the sink a config entry describes, resolving its secrets. Its
+/// requests go through `client`.
+pub fn sink_from_config(config: &SinkConfig, client: &reqwest::Client) -> Result<Box<dyn Sink>> {
+    let client = client.clone();
+    match &config.kind {
+        SinkKind::Slack { url, plan_url } => Ok(Box::new(SlackSink {
+            client,
+            webhook_url: resolve_secret(url).context("Slack sink url")?,
+            plan_url: plan_url.clone(),
+        })),
+        SinkKind::Teams { url, plan_url } => Ok(Box::new(TeamsSink {
+            client,
+            webhook_url: resolve_secret(url).context("Teams sink url")?,
+            plan_url: plan_url.clone(),
+        })),
+        SinkKind::Pagerduty {
+            routing_key,
+            severity,
+            events_url,
+            state_dir,
+        } => {
+            let routing_key = resolve_secret(routing_key).context("PagerDuty routing_key")?;
+            let state_dir = match state_dir {
+                Some(dir) => dir.clone(),
+                None => dirs::cache_dir()
+                    .context("No cache directory for PagerDuty state; set the sink's state_dir")?
+                    .join("terradrift/pagerduty"),
+            };
+            Ok(Box::new(PagerDutySink {
+                client,
+                state_dir,
+                routing_key,
+                severity: severity.clone(),
+                events_url: events_url
+                    .clone()
+                    .unwrap_or_else(|| PAGERDUTY_EVENTS_URL.to_string()),
+            }))
+        }
+        SinkKind::Webhook {
+            url,
+            headers,
+            secret,
+        } => Ok(Box::new(WebhookSink {
+            client,
+            url: resolve_secret(url).context("Webhook sink url")?,
+            headers: headers
+                .iter()
+                .map(|(name, value)| {
+                    let value = resolve_secret(value)
+                        .with_context(|| format!("Webhook sink header {name}"))?;
+                    Ok((name.clone(), value))
+                })
+                .collect::<Result<_>>()?,
+            secret: secret
+                .as_deref()
+                .map(resolve_secret)
+                .transpose()
+                .context("Webhook sink secret")?,
+        })),
+    }
+}
+
+impl NotifyWhen {
+    /// Whether a sink with this setting is notified of `summary`.
+    pub fn matches(self, summary: &RunSummary) -> bool {
+        let any = |f: fn(WorkspaceStatus) -> bool| summary.results.iter().any(|r| f(r.status));
+        match self {
+            NotifyWhen::Drift => any(|s| s == WorkspaceStatus::Drift),
+            NotifyWhen::Error => {
+                any(|s| matches!(s, WorkspaceStatus::Error | WorkspaceStatus::Timeout))
+            }
+            NotifyWhen::Always => true,
+        }
+    }
+}
+
+/// Send `summary` to every configured sink whose `when` matches. One sink
+/// failing does not keep the others from being notified; the failures are
+/// returned.
+pub async fn notify(configs: &[SinkConfig], summary: &RunSummary) -> Vec<anyhow::Error> {
+    let client = match http_client() {
+        Ok(client) => client,
+        Err(e) => return vec![e],
+    };
+    let mut errors = Vec::new();
+    for config in configs.iter().filter(|c| c.when().matches(summary)) {
+        let result = match sink_from_config(config, &client) {
+            Ok(sink) => sink
+                .send(summary)
+                .await
+                .with_context(|| format!("{} sink", sink.name())),
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            errors.push(e);
+        }
+    }
+    errors
+}
+
+/// Slack rejects messages with more blocks than this.
+const SLACK_MAX_BLOCKS: usize = 50;
+/// Slack's limit on a section's text.
+const SLACK_MAX_SECTION_CHARS: usize = 3000;
+/// Header blocks take at most this much plain text.
+const SLACK_MAX_HEADER_CHARS: usize = 150;
+/// Error messages are cut to this length in workspace lists.
+const MAX_ERROR_CHARS: usize = 200;
+/// Teams connectors reject payloads larger than about 28 KB.
+const TEAMS_MAX_PAYLOAD_BYTES: usize = 28_000;
+/// Deliveries to a throttled Teams webhook are tried this often.
+const TEAMS_ATTEMPTS: u64 = 3;
+
+fn failed(r: &WorkspaceResult) -> bool {
+    matches!(r.status, WorkspaceStatus::Error | WorkspaceStatus::Timeout)
+}
+
+/// Drifting and failing workspace counts.
+fn status_counts(summary: &RunSummary) -> (usize, usize) {
+    let drift = summary.results.iter().filter(|r| r.drift).count();
+    let errors = summary.results.iter().filter(|r| failed(r)).count();
+    (drift, errors)
+}
+
+/// Title of a chat message, e.g. `🚨 Drift detected · prod`.
+fn headline(summary: &RunSummary) -> String {
+    match status_counts(summary) {
+        (0, 0) => format!("✅ No drift · {}", summary.profile),
+        (0, _) => format!("❌ Drift scan failed · {}", summary.profile),
+        _ => format!("🚨 Drift detected · {}", summary.profile),
+    }
+}
+
+/// One-sentence outcome of the run; `bold` marks up the profile name.
+fn outcome(summary: &RunSummary, bold: impl Fn(&str) -> String) -> String {
+    let profile = bold(&summary.profile);
+    match status_counts(summary) {
+        (0, 0) => format!("✅ Terradrift found no drift for profile {profile}."),
+        (0, errors) => format!("❌ Terradrift failed to scan {errors} workspace(s) for profile {profile}."),
+        (drift, 0) => format!("🚨 Terradrift detected drift in {drift} workspace(s) for profile {profile}."),
+        (drift, errors) => format!(
+            "🚨 Terradrift detected drift in {drift} workspace(s) for profile {profile}; {errors} failed to scan."
+        ),
+    }
+}
+
+/// What a drifting or failing workspace's list entry says after its name.
+fn workspace_detail(r: &WorkspaceResult) -> String {
+    if r.status == WorkspaceStatus::Drift {
+        return format!("{} changed resource(s)", changed_label(r));
+    }
+    let error = r.error.as_deref().unwrap_or(r.status.as_str());
+    truncate(error.lines().next().unwrap_or_default(), MAX_ERROR_CHARS)
+}
+
+/// Expand a `plan_url` template for a workspace.
+fn plan_link(template: &str, summary: &RunSummary, r: &WorkspaceResult) -> String {
+    template
+        .replace("{profile}", &summary.profile)
+        .replace("{workspace}", &r.workspace)
+}
+
+/// Engines and versions the plans ran with, e.g. `terraform 1.7.5, 1.9.8`.
+fn engine_versions(summary: &RunSummary) -> Option<String> {
+    let mut versions: Vec<&str> = summary
+        .results
+        .iter()
+        .map(|r| r.terraform_version.as_str())
+        .filter(|v| !v.is_empty())
+        .collect();
+    versions.sort_unstable();
+    versions.dedup();
+    let mut engines: Vec<String> = summary
+        .results
+        .iter()
+        .map(|r| r.engine.to_string())
+        .collect();
+    engines.sort_unstable();
+    engines.dedup();
+    (!versions.is_empty()).then(|| format!("{} {}", engines.join("/"), versions.join(", ")))
+}
+
+/// Total time spent planning, across workspaces.
+fn plan_time(summary: &RunSummary) -> String {
+    duration_label(summary.results.iter().map(|r| r.duration_ms).sum())
+}
 
-pub async fn post_slack(webhook_url: &str, text: &str) -> Result<()> {
-    let payload = serde_json::json!({"text": text});
-    reqwest::Client::new()
+struct SlackSink {
+    client: reqwest::Client,
+    webhook_url: String,
+    plan_url: Option<String>,
+}
+
+#[async_trait]
+impl Sink for SlackSink {
+    fn name(&self) -> &'static str {
+        "slack"
+    }
+
+    async fn send(&self, summary: &RunSummary) -> Result<()> {
+        post_slack(
+            &self.client,
+            &self.webhook_url,
+            &slack_message(summary, self.plan_url.as_deref()),
+        )
+        .await
+    }
+}
+
+/// One-line summary, used as the notification text and under the header.
+fn slack_text(summary: &RunSummary) -> String {
+    let mut text = outcome(summary, |p| format!("*{}*", slack_escape(p)));
+    if let Some(url) = &summary.report_url {
+        text.push_str(&format!(" <{url}|View report>"));
+    }
+    text
+}
+
+/// Block Kit message: a header, the summary line, one line per drifting or
+/// failing workspace (packed into as few sections as Slack's limits allow)
+/// and a context block with run metadata.
+fn slack_message(summary: &RunSummary, plan_url: Option<&str>) -> Value {
+    let text = slack_text(summary);
+    let mut blocks = vec![
+        json!({
+            "type": "header",
+            "text": { "type": "plain_text", "text": truncate(&headline(summary), SLACK_MAX_HEADER_CHARS), "emoji": true },
+        }),
+        json!({ "type": "section", "text": { "type": "mrkdwn", "text": text } }),
+    ];
+
+    let lines: Vec<String> = summary
+        .results
+        .iter()
+        .filter(|r| r.status != WorkspaceStatus::Clean)
+        .map(|r| {
+            let mut line = format!(
+                "{} *{}* · {}",
+                status_icon(r.status),
+                slack_escape(&r.workspace),
+                slack_escape(&workspace_detail(r))
+            );
+            if let Some(template) = plan_url {
+                line.push_str(&format!(" · <{}|plan>", plan_link(template, summary, r)));
+            }
+            line
+        })
+        .collect();
+    if !lines.is_empty() {
+        blocks.push(json!({ "type": "divider" }));
+    }
+    // Room for the list: everything but the blocks above, the "more" note and
+    // the metadata context.
+    let budget = SLACK_MAX_BLOCKS - blocks.len() - 2;
+    let mut sections: Vec<String> = Vec::new();
+    let mut listed = 0;
+    for line in &lines {
+        let used = sections.len();
+        match sections.last_mut() {
+            Some(section) if section.len() + 1 + line.len() <= SLACK_MAX_SECTION_CHARS => {
+                section.push('\n');
+                section.push_str(line);
+            }
+            _ if used == budget => break,
+            _ => sections.push(truncate(line, SLACK_MAX_SECTION_CHARS)),
+        }
+        listed += 1;
+    }
+    for section in sections {
+        blocks.push(json!({ "type": "section", "text": { "type": "mrkdwn", "text": section } }));
+    }
+    if listed < lines.len() {
+        blocks.push(json!({
+            "type": "context",
+            "elements": [{ "type": "mrkdwn", "text": format!("… and {} more workspace(s)", lines.len() - listed) }],
+        }));
+    }
+
+    let mut metadata = vec![
+        format!("Profile *{}*", slack_escape(&summary.profile)),
+        format!("{} workspace(s)", summary.results.len()),
+        format!("plan time {}", plan_time(summary)),
+    ];
+    metadata.extend(engine_versions(summary));
+    blocks.push(json!({
+        "type": "context",
+        "elements": [{ "type": "mrkdwn", "text": metadata.join(" · ") }],
+    }));
+
+    json!({ "text": text, "blocks": blocks })
+}
+
+/// `90s` → `1m 30s`.
+fn duration_label(ms: u128) -> String {
+    let secs = ms / 1000;
+    match secs {
+        0..=59 => format!("{:.1}s", ms as f64 / 1000.0),
+        60..=3599 => format!("{}m {}s", secs / 60, secs % 60),
+        _ => format!("{}h {}m", secs / 3600, secs % 3600 / 60),
+    }
+}
+
+/// Escape the characters Slack's mrkdwn treats as control sequences.
+fn slack_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Cut `s` to at most `max` characters, marking the cut with an ellipsis.
+fn truncate(s: &str, max: usize) -> String {
+    if s.chars().count() <= max {
+        return s.to_string();
+    }
+    let mut out: String = s.chars().take(max - 1).collect();
+    out.push('…');
+    out
+}
+
+pub async fn post_slack(
+    client: &reqwest::Client,
+    webhook_url: &str,
+    payload: &Value,
+) -> Result<()> {
+    client
         .post(webhook_url)
-        .json(&payload)
+        .json(payload)
         .send()
         .await
-        .with_context(|| "Sending Slack webhook")?
-        .error_for_status()
-        .with_context(|| "Slack API error")?;
+        .and_then(|r| r.error_for_status())
+        // The webhook URL is the credential.
+        .map_err(reqwest::Error::without_url)
+        .context("Sending Slack webhook")?;
     Ok(())
 }
+
+struct TeamsSink {
+    client: reqwest::Client,
+    webhook_url: String,
+    plan_url: Option<String>,
+}
+
+#[async_trait]
+impl Sink for TeamsSink {
+    fn name(&self) -> &'static str {
+        "teams"
+    }
+
+    async fn send(&self, summary: &RunSummary) -> Result<()> {
+        post_teams(
+            &self.client,
+            &self.webhook_url,
+            &teams_message(summary, self.plan_url.as_deref()),
+        )
+        .await
+    }
+}
+
+/// Adaptive Card with the Slack message's content: title, outcome, a fact
+/// table, one line per drifting or failing workspace and a report button.
+/// Workspaces are dropped from the end of the list until the payload fits
+/// the connector's size limit.
+fn teams_message(summary: &RunSummary, plan_url: Option<&str>) -> Value {
+    let lines: Vec<String> = summary
+        .results
+        .iter()
+        .filter(|r| r.status != WorkspaceStatus::Clean)
+        .map(|r| {
+            let mut line = format!(
+                "{} **{}** · {}",
+                status_icon(r.status),
+                r.workspace,
+                workspace_detail(r)
+            );
+            if let Some(template) = plan_url {
+                line.push_str(&format!(" · [plan]({})", plan_link(template, summary, r)));
+            }
+            line
+        })
+        .collect();
+
+    let fits = |message: &Value| message.to_string().len() <= TEAMS_MAX_PAYLOAD_BYTES;
+    let message = teams_card(summary, &lines, lines.len());
+    if fits(&message) {
+        return message;
+    }
+    // Largest number of listed workspaces that still fits.
+    let (mut fitting, mut too_many) = (0, lines.len());
+    while too_many - fitting > 1 {
+        let mid = (fitting + too_many) / 2;
+        if fits(&teams_card(summary, &lines, mid)) {
+            fitting = mid;
+        } else {
+            too_many = mid;
+        }
+    }
+    teams_card(summary, &lines, fitting)
+}
+
+fn teams_card(summary: &RunSummary, lines: &[String], listed: usize) -> Value {
+    let (drift, errors) = status_counts(summary);
+    let mut facts = vec![
+        json!({ "title": "Profile", "value": summary.profile }),
+        json!({ "title": "Workspaces", "value": format!("{} ({drift} drifted, {errors} failed)", summary.results.len()) }),
+        json!({ "title": "Plan time", "value": plan_time(summary) }),
+    ];
+    if let Some(versions) = engine_versions(summary) {
+        facts.push(json!({ "title": "Engine", "value": versions }));
+    }
+
+    let mut body = vec![
+        json!({ "type": "TextBlock", "text": headline(summary), "size": "Large", "weight": "Bolder", "wrap": true }),
+        json!({ "type": "TextBlock", "text": outcome(summary, |p| format!("**{p}**")), "wrap": true }),
+        json!({ "type": "FactSet", "facts": facts }),
+    ];
+    for line in &lines[..listed] {
+        body.push(json!({ "type": "TextBlock", "text": line, "wrap": true, "spacing": "Small" }));
+    }
+    if listed < lines.len() {
+        body.push(json!({
+            "type": "TextBlock",
+            "text": format!("… and {} more workspace(s)", lines.len() - listed),
+            "isSubtle": true,
+            "wrap": true,
+        }));
+    }
+
+    let mut card = json!({
+        "$schema": "http://adaptivecards.io/schemas/adaptive-card.json",
+        "type": "AdaptiveCard",
+        "version": "1.4",
+        "body": body,
+        "msteams": { "width": "Full" },
+    });
+    if let Some(url) = &summary.report_url {
+        card["actions"] = json!([{ "type": "Action.OpenUrl", "title": "View report", "url": url }]);
+    }
+    json!({
+        "type": "message",
+        "attachments": [{
+            "contentType": "application/vnd.microsoft.card.adaptive",
+            "content": card,
+        }],
+    })
+}
+
+/// Post to a Teams incoming webhook. Connectors answer some failed
+/// deliveries with `200 OK` and the error in the body, and throttle busy
+/// channels with `429`, which is retried after `Retry-After`.
+async fn post_teams(client: &reqwest::Client, webhook_url: &str, payload: &Value) -> Result<()> {
+    let mut attempt = 1;
+    loop {
+        let response = client
+            .post(webhook_url)
+            .json(payload)
+            .send()
+            .await
+            // The webhook URL is the credential.
+            .map_err(reqwest::Error::without_url)
+            .context("Sending Teams webhook")?;
+        let status = response.status();
+        let retry_after = response
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|v| v.to_str().ok()?.parse::<u64>().ok());
+        let body = response.text().await.unwrap_or_default();
+        let body = body.trim();
+        let delivery_error = body.to_ascii_lowercase().contains("error");
+        let throttled = status == reqwest::StatusCode::TOO_MANY_REQUESTS
+            || (delivery_error && body.contains("429"));
+        if throttled && attempt < TEAMS_ATTEMPTS {
+            let wait = retry_after.unwrap_or(attempt).min(30);
+            tokio::time::sleep(std::time::Duration::from_secs(wait)).await;
+            attempt += 1;
+            continue;
+        }
+        if !status.is_success() || delivery_error {
+            anyhow::bail!("Teams webhook returned {status}: {body}");
+        }
+        return Ok(());
+    }
+}
+
+/// Unix time of the request, signed along with the body.
+const TIMESTAMP_HEADER: &str = "X-Terradrift-Timestamp";
+/// `sha256=<hex HMAC-SHA256 of "<timestamp>.<body>">`
+const SIGNATURE_HEADER: &str = "X-Terradrift-Signature";
+
+struct WebhookSink {
+    client: reqwest::Client,
+    url: String,
+    headers: Vec<(String, String)>,
+    secret: Option<String>,
+}
+
+#[async_trait]
+impl Sink for WebhookSink {
+    fn name(&self) -> &'static str {
+        "webhook"
+    }
+
+    /// POST the JSON summary. With a secret, the receiver recomputes the
+    /// signature over the timestamp and body and rejects stale timestamps to
+    /// stop replays.
+    async fn send(&self, summary: &RunSummary) -> Result<()> {
+        let body = summary.render(OutputFormat::Json, false)?;
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default()
+            .to_string();
+
+        let mut request = self
+            .client
+            .post(&self.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(TIMESTAMP_HEADER, &timestamp);
+        for (name, value) in &self.headers {
+            request = request.header(name, value);
+        }
+        if let Some(secret) = &self.secret {
+            request = request.header(SIGNATURE_HEADER, signature(secret, &timestamp, &body));
+        }
+        let response = request
+            .body(body)
+            .send()
+            .await
+            .map_err(reqwest::Error::without_url)
+            .with_context(|| format!("Sending webhook to {}", redact_url(&self.url)))?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            anyhow::bail!(
+                "Webhook {} returned {status}: {}",
+                redact_url(&self.url),
+                truncate(body.trim(), MAX_ERROR_CHARS)
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Scheme and host of a URL for error messages; paths and queries often
+/// carry tokens.
+fn redact_url(url: &str) -> String {
+    match reqwest::Url::parse(url) {
+        Ok(u) => match (u.host_str(), u.port()) {
+            (Some(host), Some(port)) => format!("{}://{host}:{port}", u.scheme()),
+            (Some(host), None) => format!("{}://{host}", u.scheme()),
+            (None, _) => format!("{}:", u.scheme()),
+        },
+        Err(_) => "(invalid URL)".to_string(),
+    }
+}
+
+/// Value of the signature header for a request.
+fn signature(secret: &str, timestamp: &str, body: &str) -> String {
+    let signed = format!("{timestamp}.{body}");
+    format!(
+        "sha256={}",
+        hex(&hmac_sha256(secret.as_bytes(), signed.as_bytes()))
+    )
+}
+
+/// HMAC-SHA256 (RFC 2104).
+fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    const BLOCK: usize = 64;
+    let mut block = [0u8; BLOCK];
+    if key.len() > BLOCK {
+        block[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+    let pad = |byte: u8| block.map(|b| b ^ byte);
+    let inner = Sha256::new()
+        .chain_update(pad(0x36))
+        .chain_update(message)
+        .finalize();
+    Sha256::new()
+        .chain_update(pad(0x5c))
+        .chain_update(inner)
+        .finalize()
+        .into()
+}
+
+/// Events API v2 endpoint of PagerDuty's US service region.
+const PAGERDUTY_EVENTS_URL: &str = "https://events.pagerduty.com/v2/enqueue";
+/// PagerDuty truncates longer event summaries.
+const PAGERDUTY_MAX_SUMMARY_CHARS: usize = 1024;
+/// Changed resources listed in an incident's details.
+const PAGERDUTY_MAX_RESOURCES: usize = 50;
+
+struct PagerDutySink {
+    client: reqwest::Client,
+    /// Where the workspaces with open incidents are kept between runs
+    state_dir: PathBuf,
+    routing_key: String,
+    severity: SeverityMap,
+    events_url: String,
+}
+
+/// What a PagerDuty sink remembers from earlier runs.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct PagerDutyState {
+    /// Workspaces whose incident was triggered and not yet resolved
+    open_incidents: BTreeSet<String>,
+}
+
+#[async_trait]
+impl Sink for PagerDutySink {
+    fn name(&self) -> &'static str {
+        "pagerduty"
+    }
+
+    /// Trigger (or update) an incident per drifting workspace and resolve the
+    /// incidents of workspaces that now scan clean. Failed scans leave their
+    /// workspace's incident as it is.
+    async fn send(&self, summary: &RunSummary) -> Result<()> {
+        let state_path = self.state_path(&summary.profile);
+        let mut state: PagerDutyState = match std::fs::read(&state_path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .with_context(|| format!("Parsing {}", state_path.display()))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => PagerDutyState::default(),
+            Err(e) => return Err(e).with_context(|| format!("Reading {}", state_path.display())),
+        };
+
+        let mut failures = Vec::new();
+        for r in &summary.results {
+            let drifted = r.status == WorkspaceStatus::Drift;
+            let resolved =
+                r.status == WorkspaceStatus::Clean && state.open_incidents.contains(&r.workspace);
+            if !drifted && !resolved {
+                continue;
+            }
+            let event = if drifted {
+                self.trigger_event(summary, r)
+            } else {
+                json!({
+                    "routing_key": self.routing_key,
+                    "event_action": "resolve",
+                    "dedup_key": dedup_key(summary, r),
+                })
+            };
+            match self.enqueue(&event).await {
+                Ok(()) if drifted => {
+                    state.open_incidents.insert(r.workspace.clone());
+                }
+                Ok(()) => {
+                    state.open_incidents.remove(&r.workspace);
+                }
+                Err(e) => failures.push(format!("{}: {e:#}", r.workspace)),
+            }
+        }
+
+        if let Some(dir) = state_path.parent() {
+            std::fs::create_dir_all(dir).with_context(|| format!("Creating {}", dir.display()))?;
+        }
+        write_atomic(&state_path, &serde_json::to_string_pretty(&state)?)?;
+
+        if !failures.is_empty() {
+            anyhow::bail!(
+                "{} PagerDuty event(s) failed: {}",
+                failures.len(),
+                failures.join("; ")
+            );
+        }
+        Ok(())
+    }
+}
+
+impl PagerDutySink {
+    /// State file of a profile, per routing key so sinks for different
+    /// services don't share incidents.
+    fn state_path(&self, profile: &str) -> PathBuf {
+        let key = hex(&Sha256::digest(self.routing_key.as_bytes()));
+        self.state_dir
+            .join(format!("{profile}-{}.json", &key[..12]))
+    }
+
+    fn trigger_event(&self, summary: &RunSummary, r: &WorkspaceResult) -> Value {
+        let resources: Vec<String> = r
+            .resources
+            .iter()
+            .take(PAGERDUTY_MAX_RESOURCES)
+            .map(|rc| format!("{} ({})", rc.address, rc.action.as_str()))
+            .collect();
+        let mut event = json!({
+            "routing_key": self.routing_key,
+            "event_action": "trigger",
+            "dedup_key": dedup_key(summary, r),
+            "client": "Terradrift",
+            "payload": {
+                "summary": truncate(
+                    &format!(
+                        "Drift in {}/{}: {} changed resource(s)",
+                        summary.profile,
+                        r.workspace,
+                        changed_label(r)
+                    ),
+                    PAGERDUTY_MAX_SUMMARY_CHARS
+                ),
+                "source": format!("terradrift/{}", summary.profile),
+                "severity": workspace_severity(&self.severity, r).as_str(),
+                "component": r.workspace,
+                "group": summary.profile,
+                "class": "drift",
+                "custom_details": {
+                    "state": r.state,
+                    "terraform_version": r.terraform_version,
+                    "changed_resources": changed_label(r),
+                    "resources": resources,
+                },
+            },
+        });
+        if let Some(url) = &summary.report_url {
+            event["links"] = json!([{ "href": url, "text": "Drift report" }]);
+        }
+        event
+    }
+
+    async fn enqueue(&self, event: &Value) -> Result<()> {
+        let response = self
+            .client
+            .post(&self.events_url)
+            .json(event)
+            .send()
+            .await
+            .context("Sending PagerDuty event")?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            anyhow::bail!(
+                "PagerDuty returned {status}: {}",
+                truncate(body.trim(), MAX_ERROR_CHARS)
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Identifies a workspace's incident across runs.
+fn dedup_key(summary: &RunSummary, r: &WorkspaceResult) -> String {
+    format!("terradrift/{}/{}", summary.profile, r.workspace)
+}
+
+/// Highest severity among the workspace's changed resources.
+fn workspace_severity(map: &SeverityMap, r: &WorkspaceResult) -> Severity {
+    r.resources
+        .iter()
+        .map(|rc| match rc.action {
+            ChangeAction::Create => map.create,
+            ChangeAction::Update => map.update,
+            ChangeAction::Delete => map.delete,
+            ChangeAction::Replace => map.replace,
+            ChangeAction::Read | ChangeAction::Forget => map.other,
+        })
+        .max()
+        .unwrap_or(map.other)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plan::{ChangeCounts, PendingChanges, ResourceChange};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tokio::task::JoinHandle;
+
+    /// Local HTTP stand-in: answers one request per `(status, body)` in
+    /// turn (`429`s ask for an immediate retry) and yields each request's
+    /// lowercased head and its body.
+    async fn stand_in(
+        responses: &[(u16, &'static str)],
+    ) -> (String, JoinHandle<Vec<(String, String)>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let responses = responses.to_vec();
+        let handle = tokio::spawn(async move {
+            let mut requests = Vec::new();
+            for (status, body) in responses {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut buf = Vec::new();
+                let mut chunk = [0u8; 8192];
+                let (head, len) = loop {
+                    let n = stream.read(&mut chunk).await.unwrap();
+                    assert!(n > 0, "connection closed before the request ended");
+                    buf.extend_from_slice(&chunk[..n]);
+                    if let Some(end) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+                        let head = String::from_utf8_lossy(&buf[..end]).to_ascii_lowercase();
+                        let len: usize = head
+                            .lines()
+                            .find_map(|l| l.strip_prefix("content-length:"))
+                            .map(|v| v.trim().parse().unwrap())
+                            .unwrap_or_default();
+                        buf.drain(..end + 4);
+                        break (head, len);
+                    }
+                };
+                while buf.len() < len {
+                    let n = stream.read(&mut chunk).await.unwrap();
+                    assert!(n > 0, "connection closed before the body ended");
+                    buf.extend_from_slice(&chunk[..n]);
+                }
+                let retry = if status == 429 {
+                    "retry-after: 0\r\n"
+                } else {
+                    ""
+                };
+                let response = format!(
+                    "HTTP/1.1 {status} Stand-in\r\n{retry}content-length: {}\r\nconnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                stream.write_all(response.as_bytes()).await.unwrap();
+                requests.push((head, String::from_utf8(buf).unwrap()));
+            }
+            requests
+        });
+        (url, handle)
+    }
+
+    fn summary(statuses: &[WorkspaceStatus]) -> RunSummary {
+        RunSummary {
+            profile: "prod".into(),
+            report_url: Some("https://ci.example/drift.html".into()),
+            root_dir: None,
+            results: statuses
+                .iter()
+                .enumerate()
+                .map(|(i, &status)| WorkspaceResult {
+                    error: (status == WorkspaceStatus::Error)
+                        .then(|| "terraform init failed: <exit 1>\nmore".into()),
+                    ..WorkspaceResult::fixture(&format!("ws-{i}"), status)
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn notifies_matching_runs() {
+        use WorkspaceStatus::*;
+        let clean = summary(&[Clean]);
+        let drift = summary(&[Drift, Clean]);
+        let failed = summary(&[Timeout]);
+        assert!(!NotifyWhen::Drift.matches(&clean));
+        assert!(NotifyWhen::Drift.matches(&drift));
+        assert!(!NotifyWhen::Drift.matches(&failed));
+        assert!(NotifyWhen::Error.matches(&failed));
+        assert!(!NotifyWhen::Error.matches(&drift));
+        assert!(NotifyWhen::Always.matches(&clean));
+
+        assert_eq!(
+            slack_text(&summary(&[Drift, Error])),
+            "🚨 Terradrift detected drift in 1 workspace(s) for profile *prod*; 1 failed to scan. <https://ci.example/drift.html|View report>"
+        );
+    }
+
+    #[tokio::test]
+    async fn posts_block_kit_message() {
+        use WorkspaceStatus::*;
+        let (url, requests) = stand_in(&[(200, "ok")]).await;
+        let sink = SlackSink {
+            client: http_client().unwrap(),
+            webhook_url: url,
+            plan_url: Some("https://ci.example/{profile}/{workspace}.html".into()),
+        };
+        sink.send(&summary(&[Drift, Clean, Error])).await.unwrap();
+
+        let (head, body) = &requests.await.unwrap()[0];
+        assert!(head.starts_with("post /hook "));
+        assert!(head.contains("content-type: application/json"));
+        let message: Value = serde_json::from_str(body).unwrap();
+        let blocks = message["blocks"].as_array().unwrap();
+        assert_eq!(blocks[0]["type"], "header");
+        assert_eq!(blocks[0]["text"]["text"], "🚨 Drift detected · prod");
+        assert_eq!(message["text"], blocks[1]["text"]["text"]);
+        assert_eq!(blocks[2]["type"], "divider");
+        assert_eq!(
+            blocks[3]["text"]["text"],
+            "🚨 *ws-0* · ≥1 changed resource(s) · <https://ci.example/prod/ws-0.html|plan>\n\
+             ❌ *ws-2* · terraform init failed: &lt;exit 1&gt; · <https://ci.example/prod/ws-2.html|plan>"
+        );
+        assert_eq!(
+            blocks[4]["elements"][0]["text"],
+            "Profile *prod* · 3 workspace(s) · plan time 4.5s · terraform 1.7.5"
+        );
+    }
+
+    #[tokio::test]
+    async fn truncates_to_slack_limits() {
+        let statuses = vec![WorkspaceStatus::Drift; 2000];
+        let message = slack_message(
+            &summary(&statuses),
+            Some(&format!(
+                "https://ci.example/{}/{{workspace}}",
+                "x".repeat(100)
+            )),
+        );
+        let blocks = message["blocks"].as_array().unwrap();
+        assert_eq!(blocks.len(), SLACK_MAX_BLOCKS);
+        assert!(blocks.iter().all(|b| b["text"]["text"]
+            .as_str()
+            .is_none_or(|t| t.chars().count() <= SLACK_MAX_SECTION_CHARS)));
+        let more = blocks[SLACK_MAX_BLOCKS - 2]["elements"][0]["text"]
+            .as_str()
+            .unwrap();
+        assert!(more.starts_with("… and ") && more.ends_with(" more workspace(s)"));
+
+        let (url, _requests) = stand_in(&[(500, "no_service")]).await;
+        let sink = SlackSink {
+            client: http_client().unwrap(),
+            webhook_url: url,
+            plan_url: None,
+        };
+        assert!(sink.send(&summary(&statuses)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn posts_adaptive_card() {
+        use WorkspaceStatus::*;
+        let (url, requests) = stand_in(&[(200, "1")]).await;
+        let sink = TeamsSink {
+            client: http_client().unwrap(),
+            webhook_url: url,
+            plan_url: Some("https://ci.example/{workspace}.html".into()),
+        };
+        sink.send(&summary(&[Drift, Clean, Timeout])).await.unwrap();
+
+        let (_, body) = &requests.await.unwrap()[0];
+        let message: Value = serde_json::from_str(body).unwrap();
+        let attachment = &message["attachments"][0];
+        assert_eq!(
+            attachment["contentType"],
+            "application/vnd.microsoft.card.adaptive"
+        );
+        let card = &attachment["content"];
+        assert_eq!(card["type"], "AdaptiveCard");
+        let body = card["body"].as_array().unwrap();
+        assert_eq!(body[0]["text"], "🚨 Drift detected · prod");
+        assert_eq!(
+            body[1]["text"],
+            "🚨 Terradrift detected drift in 1 workspace(s) for profile **prod**; 1 failed to scan."
+        );
+        assert_eq!(body[2]["facts"][1]["value"], "3 (1 drifted, 1 failed)");
+        assert_eq!(body[2]["facts"][3]["value"], "terraform 1.7.5");
+        assert_eq!(
+            body[3]["text"],
+            "🚨 **ws-0** · ≥1 changed resource(s) · [plan](https://ci.example/ws-0.html)"
+        );
+        assert_eq!(
+            body[4]["text"],
+            "⏱ **ws-2** · timeout · [plan](https://ci.example/ws-2.html)"
+        );
+        assert_eq!(card["actions"][0]["url"], "https://ci.example/drift.html");
+    }
+
+    #[tokio::test]
+    async fn teams_limits_and_errors() {
+        let statuses = vec![WorkspaceStatus::Error; 2000];
+        let message = teams_message(&summary(&statuses), None);
+        assert!(message.to_string().len() <= TEAMS_MAX_PAYLOAD_BYTES);
+        let body = message["attachments"][0]["content"]["body"]
+            .as_array()
+            .unwrap();
+        let more = body.last().unwrap()["text"].as_str().unwrap();
+        assert!(more.starts_with("… and ") && more.ends_with(" more workspace(s)"));
+
+        // Throttled, then a delivery failure reported in a 200 response.
+        let (url, requests) = stand_in(&[
+            (429, ""),
+            (
+                200,
+                "Microsoft Teams endpoint returned HTTP error 413 with ContextId x",
+            ),
+        ])
+        .await;
+        let sink = TeamsSink {
+            client: http_client().unwrap(),
+            webhook_url: url,
+            plan_url: None,
+        };
+        let err = sink.send(&summary(&statuses)).await.unwrap_err();
+        assert!(err.to_string().contains("HTTP error 413"), "{err}");
+        assert_eq!(requests.await.unwrap().len(), 2);
+    }
+
+    #[test]
+    fn computes_hmac_sha256() {
+        // RFC 4231, test cases 2 and 6
+        assert_eq!(
+            hex(&hmac_sha256(b"Jefe", b"what do ya want for nothing?")),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        assert_eq!(
+            hex(&hmac_sha256(
+                &[0xaa; 131],
+                b"Test Using Larger Than Block-Size Key - Hash Key First"
+            )),
+            "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54"
+        );
+    }
+
+    #[tokio::test]
+    async fn posts_signed_summary() {
+        let (url, requests) = stand_in(&[(204, "")]).await;
+        let sink = WebhookSink {
+            client: http_client().unwrap(),
+            url,
+            headers: vec![("X-Router-Token".into(), "t0ken".into())],
+            secret: Some("s3cret".into()),
+        };
+        let run = summary(&[WorkspaceStatus::Drift]);
+        sink.send(&run).await.unwrap();
+
+        let (head, body) = &requests.await.unwrap()[0];
+        assert_eq!(body, &run.render(OutputFormat::Json, false).unwrap());
+        let header = |name: &str| {
+            head.lines()
+                .find_map(|l| l.strip_prefix(&format!("{name}: ")))
+                .unwrap_or_else(|| panic!("missing {name} in {head}"))
+                .to_string()
+        };
+        assert_eq!(header("x-router-token"), "t0ken");
+        assert_eq!(header("content-type"), "application/json");
+        let timestamp = header("x-terradrift-timestamp");
+        assert_eq!(
+            header("x-terradrift-signature"),
+            signature("s3cret", &timestamp, body)
+        );
+
+        // Errors name the host only; the path may be a credential.
+        let (url, requests) = stand_in(&[(403, "denied")]).await;
+        let sink = WebhookSink {
+            client: http_client().unwrap(),
+            url: format!("{url}/t0k3n?sig=abc"),
+            headers: Vec::new(),
+            secret: None,
+        };
+        let err = format!("{:#}", sink.send(&run).await.unwrap_err());
+        requests.await.unwrap();
+        assert!(err.contains("returned 403 Forbidden: denied"), "{err}");
+        assert!(!err.contains("t0k3n") && !err.contains("sig=abc"), "{err}");
+        assert!(!err.contains("/hook"), "{err}");
+
+        let sink = WebhookSink {
+            client: http_client().unwrap(),
+            url: "http://127.0.0.1:1/hook/t0k3n".into(),
+            headers: Vec::new(),
+            secret: None,
+        };
+        let err = format!("{:#}", sink.send(&run).await.unwrap_err());
+        assert!(
+            err.starts_with("Sending webhook to http://127.0.0.1:1"),
+            "{err}"
+        );
+        assert!(!err.contains("t0k3n"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn triggers_and_resolves_pagerduty_incidents() {
+        use WorkspaceStatus::*;
+        let dir = tempfile::tempdir().unwrap();
+        let sink = |events_url: String| PagerDutySink {
+            client: http_client().unwrap(),
+            state_dir: dir.path().to_path_buf(),
+            routing_key: "R0UTING".into(),
+            severity: SeverityMap::default(),
+            events_url,
+        };
+
+        // ws-0 drifts with a delete; ws-1 is clean with no open incident.
+        let mut first = summary(&[Drift, Clean]);
+        first.results[0].resources.push(ResourceChange::fixture(
+            "aws_s3_bucket.logs",
+            ChangeAction::Delete,
+        ));
+        let (url, requests) = stand_in(&[(202, r#"{"status":"success"}"#)]).await;
+        sink(url).send(&first).await.unwrap();
+        let requests = requests.await.unwrap();
+        let event: Value = serde_json::from_str(&requests[0].1).unwrap();
+        assert_eq!(event["event_action"], "trigger");
+        assert_eq!(event["routing_key"], "R0UTING");
+        assert_eq!(event["dedup_key"], "terradrift/prod/ws-0");
+        assert_eq!(event["payload"]["severity"], "critical");
+        assert_eq!(
+            event["payload"]["summary"],
+            "Drift in prod/ws-0: ≥1 changed resource(s)"
+        );
+        assert_eq!(event["links"][0]["href"], "https://ci.example/drift.html");
+        // The state file is replaced whole, leaving no temporary file behind.
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+
+        // A failed scan keeps the incident open; a clean one resolves it.
+        let (url, requests) = stand_in(&[]).await;
+        sink(url).send(&summary(&[Error, Clean])).await.unwrap();
+        assert!(requests.await.unwrap().is_empty());
+        let (url, requests) = stand_in(&[(202, "")]).await;
+        sink(url).send(&summary(&[Clean, Clean])).await.unwrap();
+        let event: Value = serde_json::from_str(&requests.await.unwrap()[0].1).unwrap();
+        assert_eq!(event["event_action"], "resolve");
+        assert_eq!(event["dedup_key"], "terradrift/prod/ws-0");
+
+        // Nothing left to resolve.
+        let (url, requests) = stand_in(&[]).await;
+        sink(url).send(&summary(&[Clean])).await.unwrap();
+        assert!(requests.await.unwrap().is_empty());
+
+        // Unapplied configuration alone never pages.
+        let mut pending = summary(&[Clean]);
+        pending.results[0].pending = Some(PendingChanges {
+            counts: ChangeCounts {
+                delete: 1,
+                ..Default::default()
+            },
+            resources: vec![ResourceChange::fixture(
+                "aws_s3_bucket.logs",
+                ChangeAction::Delete,
+            )],
+        });
+        let (url, requests) = stand_in(&[]).await;
+        sink(url).send(&pending).await.unwrap();
+        assert!(requests.await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn maps_severity_by_action() {
+        let mut r = summary(&[WorkspaceStatus::Drift]).results.remove(0);
+        let map = SeverityMap::default();
+        assert_eq!(workspace_severity(&map, &r), Severity::Warning);
+        for action in [ChangeAction::Create, ChangeAction::Update] {
+            r.resources
+                .push(ResourceChange::fixture("null_resource.x", action));
+        }
+        assert_eq!(workspace_severity(&map, &r), Severity::Error);
+        // A pending delete does not raise the severity of real drift.
+        r.pending = Some(PendingChanges {
+            counts: ChangeCounts::default(),
+            resources: vec![ResourceChange::fixture(
+                "null_resource.y",
+                ChangeAction::Delete,
+            )],
+        });
+        assert_eq!(workspace_severity(&map, &r), Severity::Error);
+    }
+}
@@ -1,46 +1,246 @@
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::process::Stdio;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use serde_json::Value;
+use sha2::{Digest, Sha256};
 use std::io::Read;
-use tokio::io::AsyncBufReadExt;
-use tokio::process::Command;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt};
+use tokio::process::{Child, Command};
 
-const DEFAULT_TERRAFORM_VERSION: &str = "1.7.5";
+use crate::plan::{ChangeCounts, PendingChanges, ResourceChange};
+use crate::signing::Keyring;
+use crate::ui::{Diagnostic, PlanStream, UiMessage};
+
+/// Suffix of the record, next to each cached binary, of the archive digest it
+/// was verified against; e.g. `terraform.sha256`.
+const DIGEST_SUFFIX: &str = ".sha256";
+
+/// Override file that points the copied configuration at the fetched state.
+const BACKEND_OVERRIDE_FILE: &str = "terradrift_override.tf.json";
+
+/// Bytes of stderr kept per command; the oldest output is dropped first.
+const STDERR_LIMIT: usize = 64 * 1024;
+
+/// Directories never copied into a plan directory.
+const SKIP_DIRS: &[&str] = &[".terraform", ".git"];
+
+/// Terraform-compatible CLI a profile plans with.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Engine {
+    #[default]
+    Terraform,
+    Tofu,
+}
+
+impl Engine {
+    /// Executable (and release archive) name.
+    pub fn binary_name(self) -> &'static str {
+        match self {
+            Engine::Terraform => "terraform",
+            Engine::Tofu => "tofu",
+        }
+    }
+
+    fn default_version(self) -> &'static str {
+        match self {
+            Engine::Terraform => "1.7.5",
+            Engine::Tofu => "1.8.0",
+        }
+    }
+
+    fn release_url(self, version: &str) -> String {
+        match self {
+            Engine::Terraform => format!("https://releases.hashicorp.com/terraform/{version}"),
+            Engine::Tofu => {
+                format!("https://github.com/opentofu/opentofu/releases/download/v{version}")
+            }
+        }
+    }
+
+    /// Detached signature published next to `SHA256SUMS`.
+    fn signature_suffix(self) -> &'static str {
+        match self {
+            Engine::Terraform => ".sig",
+            Engine::Tofu => ".gpgsig",
+        }
+    }
+
+    /// Release signing key, pinned by primary key fingerprint.
+    fn signing_key(self) -> (&'static str, ReleaseKey) {
+        match self {
+            Engine::Terraform => (
+                "C874 011F 0AB4 0511 0D02 1055 3436 5D94 72D7 468F",
+                ReleaseKey::Embedded(include_bytes!("../keys/hashicorp.asc")),
+            ),
+            Engine::Tofu => (
+                "E3E6 E43D 84CB 852E ADB0 051D 0C0A F313 E5FD 9F80",
+                ReleaseKey::Published("https://get.opentofu.org/opentofu.asc"),
+            ),
+        }
+    }
+}
+
+/// Where an engine's release signing key comes from.
+enum ReleaseKey {
+    /// Armored key shipped in the binary.
+    Embedded(&'static [u8]),
+    /// Armored key fetched from the vendor once and cached.
+    Published(&'static str),
+}
+
+impl std::fmt::Display for Engine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.binary_name())
+    }
+}
+
+/// How much of a plan `detect_drift` reads.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScanMode {
+    /// Stop the plan at the first change; counts are a lower bound.
+    #[default]
+    Fast,
+    /// Read the plan to completion for exact counts.
+    Full,
+}
+
+impl ScanMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ScanMode::Fast => "fast",
+            ScanMode::Full => "full",
+        }
+    }
+}
+
+/// Which plan drift detection runs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PlanMode {
+    /// A normal plan: infrastructure drift and unapplied configuration alike.
+    #[default]
+    Normal,
+    /// `plan -refresh-only`: only changes made outside Terraform.
+    RefreshOnly,
+}
 
 pub struct DriftReport {
+    /// Changed resources: the total of `counts`, or in fast mode at least the
+    /// drift seen before the plan was stopped
     pub changed_resources: u64,
+    /// Changed resources per action: planned changes for a normal plan, drift
+    /// for a refresh-only one
+    pub counts: ChangeCounts,
+    /// The resources behind `counts`, plus any drift a normal plan reported
+    /// without planning a change for it
+    pub resources: Vec<ResourceChange>,
+    /// Changes a normal plan adds on top of refresh-only drift
+    pub pending: Option<PendingChanges>,
     pub drift: bool,
+    /// The plan was killed after exceeding its timeout.
+    pub timed_out: bool,
     pub duration_ms: u128,
     pub terraform_version: String,
+    /// Errors and warnings the plan reported
+    pub diagnostics: Vec<Diagnostic>,
+    /// Tail of the plan's stderr
+    pub stderr: String,
+}
+
+/// A `terraform init` or `plan` that exited with an error or was killed at
+/// its deadline, with what it reported. Recover it from an `anyhow::Error`
+/// with `downcast_ref`.
+#[derive(Debug)]
+pub struct CommandFailure {
+    pub message: String,
+    pub diagnostics: Vec<Diagnostic>,
+    /// Tail of the command's stderr
+    pub stderr: String,
+    /// The command ran past its timeout and was killed
+    pub timed_out: bool,
 }
 
-/// Ensure terraform binary for given version is present and executable.
+impl std::fmt::Display for CommandFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for CommandFailure {}
+
+/// Ensure the engine binary for given version is present and executable.
 /// Returns path to binary.
-pub async fn ensure_terraform(version: Option<&str>) -> Result<PathBuf> {
+///
+/// Downloads are only trusted once `SHA256SUMS` carries a valid signature from
+/// `keyring` (a local keyring file) or, by default, the engine vendor's pinned key.
+pub async fn ensure_terraform(
+    engine: Engine,
+    version: Option<&str>,
+    keyring: Option<&Path>,
+) -> Result<PathBuf> {
     // Check PATH first
-    if let Ok(bin) = which::which("terraform") {
+    if let Ok(bin) = which::which(engine.binary_name()) {
         return Ok(bin);
     }
 
-    let version = version.unwrap_or(DEFAULT_TERRAFORM_VERSION);
-    let cache_root = std::env::var("TERRADRIFT_TF_CACHE").unwrap_or_else(|_| {
+    install_terraform(engine, version.unwrap_or(engine.default_version()), keyring).await
+}
+
+/// Directory release binaries are cached in, one subdirectory per version.
+pub fn cache_root() -> PathBuf {
+    PathBuf::from(std::env::var("TERRADRIFT_TF_CACHE").unwrap_or_else(|_| {
         dirs::cache_dir()
             .unwrap_or_else(|| PathBuf::from("/tmp"))
             .join("terradrift/terraform")
             .to_string_lossy()
             .to_string()
-    });
-    let bin_path = Path::new(&cache_root).join(version).join("terraform");
-    if bin_path.exists() {
-        return Ok(bin_path);
+    }))
+}
+
+/// Verified binary for `version` in the cache, if present.
+pub fn cached_binary(engine: Engine, version: &str) -> Option<PathBuf> {
+    let bin_path = cache_root().join(version).join(engine.binary_name());
+    let digest_path = bin_path.with_file_name(format!("{}{DIGEST_SUFFIX}", engine.binary_name()));
+    // Binaries cached before checksum verification have no digest record and
+    // are fetched again.
+    (bin_path.exists() && digest_path.exists()).then_some(bin_path)
+}
+
+/// Versions with a verified cached binary for `engine`.
+pub fn cached_versions(engine: Engine) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(cache_root()) else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|e| e.ok()?.file_name().into_string().ok())
+        .filter(|v| cached_binary(engine, v).is_some())
+        .collect()
+}
+
+/// Return the cached binary for exactly `version`, downloading and verifying
+/// it first if needed. Several versions are cached side by side.
+pub async fn install_terraform(
+    engine: Engine,
+    version: &str,
+    keyring: Option<&Path>,
+) -> Result<PathBuf> {
+    if let Some(bin) = cached_binary(engine, version) {
+        return Ok(bin);
     }
+    let cache_root = cache_root();
+    let bin_path = cache_root.join(version).join(engine.binary_name());
+    let digest_path = bin_path.with_file_name(format!("{}{DIGEST_SUFFIX}", engine.binary_name()));
     fs::create_dir_all(bin_path.parent().unwrap())?;
 
-    download_terraform(version, &bin_path).await?;
+    let keyring = release_keyring(engine, keyring, &cache_root).await?;
+    let base = engine.release_url(version);
+    let (file_name, digest) =
+        download_terraform(engine, version, &base, &bin_path, &keyring).await?;
 
     #[cfg(unix)]
     {
@@ -50,24 +250,98 @@ pub async fn ensure_terraform(version: Option<&str>) -> Result<PathBuf> {
         fs::set_permissions(&bin_path, perms)?;
     }
 
+    // Same layout as a SHA256SUMS line, so `sha256sum -c` style tooling can read it.
+    fs::write(&digest_path, format!("{digest}  {file_name}\n"))?;
+
     Ok(bin_path)
 }
 
-async fn download_terraform(version: &str, dst_bin: &Path) -> Result<()> {
+/// Load the keyring release signatures are checked against: the configured
+/// file, or the engine's pinned release key.
+async fn release_keyring(
+    engine: Engine,
+    keyring: Option<&Path>,
+    cache_root: &Path,
+) -> Result<Keyring> {
+    if let Some(path) = keyring {
+        let data = tokio::fs::read(path)
+            .await
+            .with_context(|| format!("Reading release keyring {}", path.display()))?;
+        return Keyring::from_bytes(&data)
+            .with_context(|| format!("Loading release keyring {}", path.display()));
+    }
+
+    let (fingerprint, key) = engine.signing_key();
+    let url = match key {
+        ReleaseKey::Embedded(data) => {
+            return Keyring::from_bytes(data)
+                .and_then(|k| k.pin(fingerprint))
+                .with_context(|| {
+                    format!("Embedded {engine} release key failed fingerprint check")
+                });
+        }
+        ReleaseKey::Published(url) => url,
+    };
+    let cached = cache_root
+        .join("keys")
+        .join(format!("{}.asc", engine.binary_name()));
+    if let Ok(data) = tokio::fs::read(&cached).await {
+        if let Ok(keyring) = Keyring::from_bytes(&data).and_then(|k| k.pin(fingerprint)) {
+            return Ok(keyring);
+        }
+    }
+
+    let data = fetch_bytes(url)
+        .await
+        .with_context(|| format!("Fetching {engine} release key"))?;
+    let keyring = Keyring::from_bytes(&data)
+        .and_then(|k| k.pin(fingerprint))
+        .with_context(|| format!("{engine} release key failed fingerprint check"))?;
+    fs::create_dir_all(cached.parent().unwrap())?;
+    fs::write(&cached, &data)?;
+    Ok(keyring)
+}
+
+/// Download the release zip from `base`, verify it against the signed
+/// SHA256SUMS and extract the binary to `dst_bin`. Returns the archive name and
+/// its verified digest.
+async fn download_terraform(
+    engine: Engine,
+    version: &str,
+    base: &str,
+    dst_bin: &Path,
+    keyring: &Keyring,
+) -> Result<(String, String)> {
     let (os, arch) = platform_triple();
-    let file_name = format!("terraform_{version}_{os}_{arch}.zip");
-    let url = format!("https://releases.hashicorp.com/terraform/{version}/{file_name}");
+    let name = engine.binary_name();
+    let file_name = format!("{name}_{version}_{os}_{arch}.zip");
 
+    let sums_name = format!("{name}_{version}_SHA256SUMS");
+    let sums = fetch_bytes(&format!("{base}/{sums_name}")).await?;
+    let sig = fetch_bytes(&format!("{base}/{sums_name}{}", engine.signature_suffix())).await?;
+    keyring
+        .verify_detached(&sums, &sig)
+        .with_context(|| format!("Refusing to trust {sums_name}: signature verification failed"))?;
+    let expected = expected_sha256(&String::from_utf8_lossy(&sums), &file_name)?;
+
+    let url = format!("{base}/{file_name}");
     let resp = reqwest::get(&url).await?.error_for_status()?;
     let bytes = resp.bytes().await?;
 
+    let digest = sha256_hex(&bytes);
+    if digest != expected {
+        anyhow::bail!(
+            "Checksum mismatch for {file_name}: SHA256SUMS lists {expected}, downloaded archive is {digest}"
+        );
+    }
+
     let dst_path = dst_bin.to_path_buf();
 
     // Offload decompression to blocking thread
     tokio::task::spawn_blocking(move || -> Result<()> {
         let reader = std::io::Cursor::new(bytes);
         let mut zip = zip::ZipArchive::new(reader)?;
-        let mut file = zip.by_name("terraform")?;
+        let mut file = zip.by_name(name)?;
         let mut buffer = Vec::new();
         file.read_to_end(&mut buffer)?;
         std::fs::write(&dst_path, &buffer)?;
@@ -75,10 +349,32 @@ async fn download_terraform(version: &str, dst_bin: &Path) -> Result<()> {
     })
     .await??;
 
-    Ok(())
+    Ok((file_name, digest))
+}
+
+async fn fetch_bytes(url: &str) -> Result<Vec<u8>> {
+    let resp = reqwest::get(url)
+        .await?
+        .error_for_status()
+        .with_context(|| format!("Fetching {url}"))?;
+    Ok(resp.bytes().await?.to_vec())
 }
 
-/// Detect host OS/ARCH to construct official terraform package names.
+/// Look up the digest listed for `file_name` in a SHA256SUMS document.
+fn expected_sha256(sums: &str, file_name: &str) -> Result<String> {
+    sums.lines()
+        .filter_map(|line| line.split_once(char::is_whitespace))
+        .find(|(_, name)| name.trim_start().trim_start_matches('*') == file_name)
+        .map(|(digest, _)| digest.to_ascii_lowercase())
+        .with_context(|| format!("{file_name} not listed in SHA256SUMS"))
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+/// Detect host OS/ARCH to construct official release package names
+/// (HashiCorp and OpenTofu use the same scheme).
 fn platform_triple() -> (&'static str, &'static str) {
     let os = match std::env::consts::OS {
         "linux" => "linux",
@@ -93,7 +389,19 @@ fn platform_triple() -> (&'static str, &'static str) {
     (os, arch)
 }
 
-/// Run `terraform version -json` to capture version string
+/// Read the `terraform_version` that last wrote a state file, if recorded.
+pub async fn state_terraform_version(state_path: &Path) -> Result<Option<String>> {
+    let data = tokio::fs::read(state_path)
+        .await
+        .with_context(|| format!("Reading state {}", state_path.display()))?;
+    let v: Value = serde_json::from_slice(&data).unwrap_or(Value::Null);
+    Ok(v["terraform_version"]
+        .as_str()
+        .filter(|s| !s.is_empty())
+        .map(str::to_string))
+}
+
+/// Run `<engine> version -json` to capture version string
 pub async fn terraform_version(bin: &Path) -> Result<String> {
     let output = Command::new(bin)
         .arg("version")
@@ -103,71 +411,680 @@ pub async fn terraform_version(bin: &Path) -> Result<String> {
         .wait_with_output()
         .await?;
     let v: serde_json::Value = serde_json::from_slice(&output.stdout)?;
-    Ok(v["terraform_version"]
+    Ok(parse_version_json(&v))
+}
+
+/// OpenTofu keeps Terraform's `terraform_version` key; `tofu_version` is
+/// accepted too in case that ever changes.
+fn parse_version_json(v: &Value) -> String {
+    v["terraform_version"]
         .as_str()
+        .or_else(|| v["tofu_version"].as_str())
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// Directory a plan runs in, together with the state it runs against.
+pub struct PlanDir {
+    /// Working directory for `terraform init`/`plan`.
+    pub dir: PathBuf,
+    /// State file the plan is evaluated against.
+    pub state: PathBuf,
+    /// Scratch copy to delete once the plan is done (`None` in legacy mode).
+    scratch: Option<PathBuf>,
+}
+
+impl PlanDir {
+    /// Legacy mode: plan in the current directory against whatever backend it
+    /// configures. The fetched state is not wired in.
+    pub fn current(state: &Path) -> Self {
+        PlanDir {
+            dir: PathBuf::from("."),
+            state: state.to_path_buf(),
+            scratch: None,
+        }
+    }
+
+    /// Remove the scratch copy, if any.
+    pub async fn cleanup(self) {
+        if let Some(scratch) = self.scratch {
+            let _ = tokio::fs::remove_dir_all(scratch).await;
+        }
+    }
+}
+
+/// Copy the configuration tree under `root_dir` into a scratch directory and
+/// wire the fetched state into it through a local backend override.
+///
+/// The whole root is copied so relative module sources (`../modules/x`) keep
+/// resolving; the plan then runs in the copy of `config_dir`.
+pub async fn prepare_plan_dir(
+    root_dir: &Path,
+    config_dir: &Path,
+    state_path: &Path,
+) -> Result<PlanDir> {
+    if !config_dir.is_dir() {
+        anyhow::bail!(
+            "Terraform configuration directory not found: {}",
+            config_dir.display()
+        );
+    }
+    // Resolve `..` and symlinks first, so a `workspace_dir` template cannot
+    // point outside `root_dir`.
+    let root = fs::canonicalize(root_dir)
+        .with_context(|| format!("Resolving root_dir {}", root_dir.display()))?;
+    let rel = fs::canonicalize(config_dir)
+        .with_context(|| format!("Resolving {}", config_dir.display()))?
+        .strip_prefix(&root)
+        .map(Path::to_path_buf)
+        .with_context(|| {
+            format!(
+                "Workspace directory {} is outside root_dir {}",
+                config_dir.display(),
+                root_dir.display()
+            )
+        })?;
+
+    let scratch = std::env::temp_dir().join(format!("terradrift_{}", uuid::Uuid::new_v4()));
+    let src = root_dir.to_path_buf();
+    let dst = scratch.clone();
+    if let Err(e) = tokio::task::spawn_blocking(move || copy_tree(&src, &dst)).await? {
+        let _ = tokio::fs::remove_dir_all(&scratch).await;
+        return Err(e);
+    }
+
+    let dir = scratch.join(rel);
+    let state = dir.join("terradrift.tfstate");
+    tokio::fs::copy(state_path, &state)
+        .await
+        .with_context(|| format!("Copying state {}", state_path.display()))?;
+
+    let backend = serde_json::json!({
+        "terraform": { "backend": { "local": { "path": state.to_string_lossy() } } }
+    });
+    tokio::fs::write(
+        dir.join(BACKEND_OVERRIDE_FILE),
+        serde_json::to_vec_pretty(&backend)?,
+    )
+    .await?;
+
+    Ok(PlanDir {
+        dir,
+        state,
+        scratch: Some(scratch),
+    })
+}
+
+/// The configuration file reports point at for a workspace, relative to
+/// `root_dir`: the `*.tf` file declaring the backend, else `main.tf`, else the
+/// first `*.tf` file.
+pub fn config_file(root_dir: &Path, config_dir: &Path) -> Option<PathBuf> {
+    let mut files: Vec<PathBuf> = fs::read_dir(config_dir)
+        .ok()?
+        .filter_map(|e| Some(e.ok()?.path()))
+        .filter(|p| p.extension().is_some_and(|ext| ext == "tf") && p.is_file())
+        .collect();
+    files.sort();
+    let declares_backend = |path: &PathBuf| {
+        fs::read_to_string(path).is_ok_and(|text| {
+            text.lines()
+                .any(|l| l.trim_start().starts_with("backend \""))
+        })
+    };
+    let file = files
+        .iter()
+        .find(|p| declares_backend(p))
+        .or_else(|| files.iter().find(|p| p.ends_with("main.tf")))
+        .or(files.first())?;
+    file.strip_prefix(root_dir).ok().map(Path::to_path_buf)
+}
+
+fn copy_tree(src: &Path, dst: &Path) -> Result<()> {
+    copy_dir(src, dst, &mut Vec::new())
+}
+
+/// Copy `src` into `dst`, following symlinks so linked files and modules are
+/// materialised in the copy. `ancestors` are the resolved directories being
+/// copied above `src`; a link back to one of them is a cycle.
+fn copy_dir(src: &Path, dst: &Path, ancestors: &mut Vec<PathBuf>) -> Result<()> {
+    let resolved = fs::canonicalize(src).with_context(|| format!("Resolving {}", src.display()))?;
+    if ancestors.contains(&resolved) {
+        anyhow::bail!("Symlink cycle at {}", src.display());
+    }
+    ancestors.push(resolved);
+    fs::create_dir_all(dst)?;
+    for entry in fs::read_dir(src)? {
+        let entry = entry?;
+        let path = entry.path();
+        let name = entry.file_name();
+        let target = dst.join(&name);
+        let metadata =
+            fs::metadata(&path).with_context(|| format!("Reading {}", path.display()))?;
+        if metadata.is_dir() {
+            if SKIP_DIRS.iter().any(|d| name == *d) {
+                continue;
+            }
+            copy_dir(&path, &target, ancestors)?;
+        } else {
+            fs::copy(&path, &target).with_context(|| format!("Copying {}", path.display()))?;
+        }
+    }
+    ancestors.pop();
+    Ok(())
+}
+
+/// Run `terraform init` in a prepared plan directory. With a `timeout`, its
+/// whole process tree is killed once it elapses, as for plans.
+pub async fn init_plan_dir(
+    bin: &Path,
+    plan_dir: &PlanDir,
+    timeout: Option<Duration>,
+) -> Result<()> {
+    let mut cmd = Command::new(bin);
+    cmd.current_dir(&plan_dir.dir)
+        .arg("init")
+        .arg("-input=false")
+        .arg("-no-color")
+        .arg("-reconfigure");
+    // Own process group, so provider downloads can be killed along with terraform.
+    #[cfg(unix)]
+    cmd.process_group(0);
+
+    let mut child = cmd.stdout(Stdio::null()).stderr(Stdio::piped()).spawn()?;
+    let stderr = capture_stderr(&mut child);
+    let status = match timeout {
+        Some(limit) => tokio::time::timeout(limit, child.wait()).await.ok(),
+        None => Some(child.wait().await),
+    };
+    let Some(status) = status else {
+        kill_tree(&mut child).await;
+        let _ = child.wait().await;
+        return Err(CommandFailure {
+            message: format!("terraform init timed out in {}", plan_dir.dir.display()),
+            diagnostics: Vec::new(),
+            stderr: collect_stderr(stderr).await,
+            timed_out: true,
+        }
+        .into());
+    };
+    let status = status?;
+    let stderr = collect_stderr(stderr).await;
+    if !status.success() {
+        return Err(CommandFailure {
+            message: format!(
+                "terraform init failed in {} ({status}){}",
+                plan_dir.dir.display(),
+                last_line(&stderr)
+            ),
+            diagnostics: Vec::new(),
+            stderr,
+            timed_out: false,
+        }
+        .into());
+    }
+    Ok(())
+}
+
+/// Drain a child's stderr in the background, keeping the last
+/// `STDERR_LIMIT` bytes.
+fn capture_stderr(child: &mut Child) -> tokio::task::JoinHandle<String> {
+    let stderr = child.stderr.take();
+    tokio::spawn(async move {
+        let Some(mut stderr) = stderr else {
+            return String::new();
+        };
+        let mut tail = Vec::new();
+        let mut buf = [0u8; 8192];
+        while let Ok(n) = stderr.read(&mut buf).await {
+            if n == 0 {
+                break;
+            }
+            tail.extend_from_slice(&buf[..n]);
+            if tail.len() > STDERR_LIMIT {
+                tail.drain(..tail.len() - STDERR_LIMIT);
+            }
+        }
+        String::from_utf8_lossy(&tail).trim().to_string()
+    })
+}
+
+/// Wait briefly for captured stderr; a leftover plugin holding the pipe open
+/// must not stall the scan.
+async fn collect_stderr(handle: tokio::task::JoinHandle<String>) -> String {
+    match tokio::time::timeout(Duration::from_secs(2), handle).await {
+        Ok(Ok(stderr)) => stderr,
+        _ => String::new(),
+    }
+}
+
+/// `": <last line>"` of captured output, for error messages.
+fn last_line(output: &str) -> String {
+    output
+        .lines()
+        .rev()
+        .find(|l| !l.trim().is_empty())
+        .map(|l| format!(": {}", l.trim()))
         .unwrap_or_default()
-        .to_string())
 }
 
 /// Stub drift detection – just runs `terraform version` for now.
 pub async fn detect_drift_stub(bin: &Path) -> Result<DriftReport> {
-    detect_drift(bin, Path::new("/dev/null")).await
+    detect_drift(
+        bin,
+        &PlanDir::current(Path::new("/dev/null")),
+        None,
+        ScanMode::Fast,
+        PlanMode::Normal,
+    )
+    .await
 }
 
-pub async fn detect_drift(bin: &Path, state_path: &Path) -> Result<DriftReport> {
+/// Run the plan and report drift. With a `timeout`, the plan's whole process
+/// tree is killed once it elapses and the report is marked `timed_out`.
+pub async fn detect_drift(
+    bin: &Path,
+    plan_dir: &PlanDir,
+    timeout: Option<Duration>,
+    mode: ScanMode,
+    plan_mode: PlanMode,
+) -> Result<DriftReport> {
     let start = Instant::now();
 
     let mut cmd = Command::new(bin);
-    cmd.arg("plan")
+    cmd.current_dir(&plan_dir.dir)
+        .arg("plan")
         .arg("-detailed-exitcode")
         .arg("-input=false")
         .arg("-no-color")
-        .arg("-refresh=true")
-        .arg("-json")
-        .env("TF_STATE", state_path);
+        .arg("-json");
+    match plan_mode {
+        PlanMode::Normal => cmd.arg("-refresh=true"),
+        PlanMode::RefreshOnly => cmd.arg("-refresh-only"),
+    };
+    if plan_dir.scratch.is_some() {
+        // Scratch copies own their state; nothing else can contend for the lock.
+        cmd.arg("-lock=false");
+    }
+    // Own process group, so provider plugins can be killed along with terraform.
+    #[cfg(unix)]
+    cmd.process_group(0);
 
-    let mut child = cmd.stdout(Stdio::piped()).stderr(Stdio::null()).spawn()?;
+    let mut child = cmd.stdout(Stdio::piped()).stderr(Stdio::piped()).spawn()?;
     let stdout = child.stdout.take().expect("child stdout");
+    let stderr = capture_stderr(&mut child);
 
     let mut reader = tokio::io::BufReader::new(stdout).lines();
-    let mut changed = 0u64;
-
-    while let Some(line) = reader.next_line().await? {
-        let v: Value = match serde_json::from_str(&line) {
-            Ok(val) => val,
-            Err(_) => continue, // skip invalid fragments
-        };
-        if let Some(arr) = v.get("resource_changes").and_then(|v| v.as_array()) {
-            for rc in arr {
-                if let Some(actions) = rc
-                    .get("change")
-                    .and_then(|c| c.get("actions"))
-                    .and_then(|a| a.as_array())
-                {
-                    let only_noop =
-                        actions.len() == 1 && actions[0].as_str().unwrap_or("") == "no-op";
-                    if !only_noop {
-                        changed += 1;
+    let scan = async {
+        let mut stream = PlanStream::default();
+        while let Some(line) = reader.next_line().await? {
+            if let Some(msg) = UiMessage::parse(&line) {
+                stream.push(msg);
+            } else if let Ok(v) = serde_json::from_str::<Value>(&line) {
+                // `terraform show -json`-style plan documents.
+                if let Some(arr) = v.get("resource_changes").and_then(|v| v.as_array()) {
+                    for rc in arr.iter().filter_map(ResourceChange::from_plan_json) {
+                        stream.planned.record(rc.action);
+                        stream.resources.push(rc);
                     }
                 }
             }
+
+            if mode == ScanMode::Fast && stream.has_drift() {
+                // Early exit: the caller kills the plan.
+                return Ok::<_, anyhow::Error>((stream, None));
+            }
         }
+        Ok((stream, Some(child.wait().await?)))
+    };
 
-        if changed > 0 {
-            let _ = child.kill().await;
-            break;
+    let outcome = match timeout {
+        Some(limit) => tokio::time::timeout(limit, scan).await.ok(),
+        None => Some(scan.await),
+    };
+    let (stream, status, timed_out) = match outcome {
+        Some(res) => {
+            let (stream, status) = res?;
+            (stream, status, false)
         }
-    }
+        None => (PlanStream::default(), None, true),
+    };
+    let counts = match plan_mode {
+        PlanMode::Normal => stream.planned,
+        // Refresh-only plans report drift and plan nothing.
+        PlanMode::RefreshOnly => ChangeCounts::from_resources(&stream.resources),
+    };
+    // A fast scan can stop at the first `resource_drift`, before any
+    // `planned_change` arrives.
+    let changed_resources = match mode {
+        ScanMode::Full => counts.total(),
+        ScanMode::Fast => counts.total().max(stream.drifted),
+    };
 
-    // Ensure the child process has terminated.
-    let status = child.wait().await.unwrap_or_default();
+    // Ensure the plan (and any provider plugins) has terminated.
+    let status = match status {
+        // -detailed-exitcode: 0 = no changes, 2 = changes, anything else failed.
+        Some(status) if !matches!(status.code(), Some(0) | Some(2)) => {
+            let stderr = collect_stderr(stderr).await;
+            let errors = stream.errors();
+            let reason = if errors.is_empty() {
+                last_line(&stderr)
+            } else {
+                format!(": {}", errors.join("; "))
+            };
+            return Err(CommandFailure {
+                message: format!(
+                    "terraform plan failed in {} ({status}){reason}",
+                    plan_dir.dir.display()
+                ),
+                diagnostics: stream.diagnostics,
+                stderr,
+                timed_out: false,
+            }
+            .into());
+        }
+        Some(status) => status,
+        None => {
+            kill_tree(&mut child).await;
+            child.wait().await.unwrap_or_default()
+        }
+    };
 
-    let tf_version = terraform_version(bin).await.unwrap_or_default();
+    let drift = !timed_out && (status.code() == Some(2) || stream.has_drift());
+    let mut diagnostics = stream.diagnostics;
+    // Only a normal plan's summary counts planned changes.
+    let summary = stream.summary.filter(|_| plan_mode == PlanMode::Normal);
+    if let Some(summary) = summary.filter(|s| !s.matches(&counts)) {
+        diagnostics.push(Diagnostic {
+            severity: "warning".into(),
+            summary: "Change counts disagree with the plan summary".into(),
+            detail: format!(
+                "Terradrift counted {} created, {} updated, {} deleted and {} replaced; \
+                     the plan reports {} to add, {} to change and {} to destroy.",
+                counts.create,
+                counts.update,
+                counts.delete,
+                counts.replace,
+                summary.add,
+                summary.change,
+                summary.remove
+            ),
+            address: None,
+        });
+    }
+
+    let tf_version = match stream.version.clone() {
+        Some(v) => v,
+        None => terraform_version(bin).await.unwrap_or_default(),
+    };
 
     Ok(DriftReport {
-        changed_resources: changed,
-        drift: status.code() == Some(2) || changed > 0,
+        changed_resources,
+        counts,
+        pending: None,
+        drift,
+        resources: stream.resources,
+        timed_out,
         duration_ms: start.elapsed().as_millis(),
         terraform_version: tf_version,
+        diagnostics,
+        stderr: collect_stderr(stderr).await,
     })
 }
+
+/// Kill a plan started in its own process group together with everything it spawned.
+async fn kill_tree(child: &mut Child) {
+    #[cfg(unix)]
+    if let Some(pid) = child.id() {
+        // SAFETY: kill(2) has no memory-safety preconditions; a negative pid
+        // addresses the process group created for the plan.
+        unsafe {
+            libc::kill(-(pid as libc::pid_t), libc::SIGKILL);
+        }
+    }
+    let _ = child.kill().await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    const SUMS: &str = "\
+5f9c7aa76b7c34d722fc9123208e26b22d60440cb47150dd04733b9b94f4541a  terraform_1.7.5_darwin_arm64.zip
+3ff056b5e8259003f67fd0f0ed7229499cfb0b41f3ff55cc184088589994f7a5  terraform_1.7.5_linux_amd64.zip
+";
+
+    #[test]
+    fn sha256sums_lookup() {
+        assert_eq!(
+            expected_sha256(SUMS, "terraform_1.7.5_linux_amd64.zip").unwrap(),
+            "3ff056b5e8259003f67fd0f0ed7229499cfb0b41f3ff55cc184088589994f7a5"
+        );
+        assert!(expected_sha256(SUMS, "terraform_1.7.5_windows_amd64.zip").is_err());
+    }
+
+    #[test]
+    fn version_json_for_both_engines() {
+        let tf = serde_json::json!({"terraform_version": "1.7.5", "platform": "linux_amd64"});
+        let tofu = serde_json::json!({"tofu_version": "1.8.0"});
+        assert_eq!(parse_version_json(&tf), "1.7.5");
+        assert_eq!(parse_version_json(&tofu), "1.8.0");
+    }
+
+    #[test]
+    fn sha256_digest_is_lowercase_hex() {
+        assert_eq!(
+            sha256_hex(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    const RELEASE_KEY: &[u8] = include_bytes!("../tests/fixtures/release/release-key.asc");
+    const SIGNED_SUMS: &[u8] =
+        include_bytes!("../tests/fixtures/release/terraform_1.7.5_SHA256SUMS");
+    const SUMS_SIG: &[u8] =
+        include_bytes!("../tests/fixtures/release/terraform_1.7.5_SHA256SUMS.sig");
+
+    /// Serve `files` by path until the test ends; anything else is a 404.
+    async fn release_server(files: Vec<(String, Vec<u8>)>) -> String {
+        use tokio::io::AsyncWriteExt;
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}/v1.7.5", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut buf = vec![0u8; 4096];
+                let n = stream.read(&mut buf).await.unwrap();
+                let head = String::from_utf8_lossy(&buf[..n]);
+                let path = head.split_whitespace().nth(1).unwrap_or_default();
+                let file = files
+                    .iter()
+                    .find(|(name, _)| path == format!("/v1.7.5/{name}"));
+                let (status, body) = match file {
+                    Some((_, body)) => ("200 OK", body.as_slice()),
+                    None => ("404 Not Found", &b""[..]),
+                };
+                let head = format!(
+                    "HTTP/1.1 {status}\r\ncontent-length: {}\r\nconnection: close\r\n\r\n",
+                    body.len()
+                );
+                stream.write_all(head.as_bytes()).await.unwrap();
+                stream.write_all(body).await.unwrap();
+            }
+        });
+        base
+    }
+
+    #[test]
+    fn embedded_release_key_matches_pin() {
+        let (fingerprint, ReleaseKey::Embedded(data)) = Engine::Terraform.signing_key() else {
+            panic!("HashiCorp release key is not embedded");
+        };
+        Keyring::from_bytes(data).unwrap().pin(fingerprint).unwrap();
+    }
+
+    #[tokio::test]
+    async fn download_fails_closed_on_bad_signature() {
+        let mut tampered = SIGNED_SUMS.to_vec();
+        tampered[0] = if tampered[0] == b'0' { b'1' } else { b'0' };
+        let base = release_server(vec![
+            ("terraform_1.7.5_SHA256SUMS".into(), tampered),
+            ("terraform_1.7.5_SHA256SUMS.sig".into(), SUMS_SIG.to_vec()),
+        ])
+        .await;
+        let dir = tempdir().unwrap();
+        let bin = dir.path().join("terraform");
+        let keyring = Keyring::from_bytes(RELEASE_KEY).unwrap();
+
+        let err = download_terraform(Engine::Terraform, "1.7.5", &base, &bin, &keyring)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("signature verification failed"));
+        assert!(!bin.exists());
+    }
+
+    // The signed fixture only lists archives for these platforms.
+    #[cfg(any(
+        all(target_os = "linux", target_arch = "x86_64"),
+        all(target_os = "macos", target_arch = "aarch64")
+    ))]
+    #[tokio::test]
+    async fn download_fails_closed_on_checksum_mismatch() {
+        let (os, arch) = platform_triple();
+        let base = release_server(vec![
+            ("terraform_1.7.5_SHA256SUMS".into(), SIGNED_SUMS.to_vec()),
+            ("terraform_1.7.5_SHA256SUMS.sig".into(), SUMS_SIG.to_vec()),
+            (
+                format!("terraform_1.7.5_{os}_{arch}.zip"),
+                b"not the signed archive".to_vec(),
+            ),
+        ])
+        .await;
+        let dir = tempdir().unwrap();
+        let bin = dir.path().join("terraform");
+        let keyring = Keyring::from_bytes(RELEASE_KEY).unwrap();
+
+        let err = download_terraform(Engine::Terraform, "1.7.5", &base, &bin, &keyring)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().starts_with("Checksum mismatch"));
+        assert!(!bin.exists());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn fast_scan_counts_drift_reported_before_planned_changes() {
+        use std::os::unix::fs::PermissionsExt;
+        let change = r#"{"resource":{"addr":"aws_instance.web","module":"","resource_type":"aws_instance","implied_provider":"aws"},"action":"update"}"#;
+        let dir = tempdir().unwrap();
+        let bin = dir.path().join("terraform");
+        fs::write(
+            &bin,
+            format!(
+                "#!/bin/sh\necho '{{\"type\":\"version\",\"terraform\":\"1.7.5\",\"ui\":\"1.2\"}}'\n\
+                 echo '{{\"type\":\"resource_drift\",\"change\":{change}}}'\nsleep 60\n"
+            ),
+        )
+        .unwrap();
+        fs::set_permissions(&bin, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let plan_dir = PlanDir::current(&dir.path().join("state"));
+        let report = detect_drift(
+            &bin,
+            &plan_dir,
+            Some(Duration::from_secs(30)),
+            ScanMode::Fast,
+            PlanMode::Normal,
+        )
+        .await
+        .unwrap();
+        assert!(report.drift);
+        assert_eq!(report.counts, ChangeCounts::default());
+        assert_eq!(report.changed_resources, 1);
+    }
+
+    #[tokio::test]
+    async fn plan_dir_copies_tree_and_overrides_backend() {
+        let root = tempdir().unwrap();
+        let ws_dir = root.path().join("envs/prod");
+        fs::create_dir_all(&ws_dir).unwrap();
+        fs::create_dir_all(root.path().join("modules/net")).unwrap();
+        fs::create_dir_all(ws_dir.join(".terraform")).unwrap();
+        fs::write(
+            ws_dir.join("main.tf"),
+            "module \"net\" { source = \"../../modules/net\" }",
+        )
+        .unwrap();
+        fs::write(root.path().join("modules/net/main.tf"), "").unwrap();
+        let state = root.path().join("prod.tfstate");
+        fs::write(&state, b"{\"version\":4}").unwrap();
+
+        let plan_dir = prepare_plan_dir(root.path(), &ws_dir, &state)
+            .await
+            .unwrap();
+        assert!(plan_dir.dir.join("main.tf").exists());
+        assert!(plan_dir.dir.join("../../modules/net/main.tf").exists());
+        assert!(!plan_dir.dir.join(".terraform").exists());
+        assert_eq!(fs::read(&plan_dir.state).unwrap(), b"{\"version\":4}");
+
+        let over: Value =
+            serde_json::from_slice(&fs::read(plan_dir.dir.join(BACKEND_OVERRIDE_FILE)).unwrap())
+                .unwrap();
+        assert_eq!(
+            over["terraform"]["backend"]["local"]["path"],
+            plan_dir.state.to_string_lossy().as_ref()
+        );
+
+        let scratch = plan_dir.scratch.clone().unwrap();
+        plan_dir.cleanup().await;
+        assert!(!scratch.exists());
+
+        // Linked modules are copied as directories; a link loop is an error.
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(root.path().join("modules/net"), ws_dir.join("net"))
+                .unwrap();
+            let plan_dir = prepare_plan_dir(root.path(), &ws_dir, &state)
+                .await
+                .unwrap();
+            assert!(plan_dir.dir.join("net/main.tf").is_file());
+            plan_dir.cleanup().await;
+
+            std::os::unix::fs::symlink(root.path().join("envs"), ws_dir.join("loop")).unwrap();
+            let err = prepare_plan_dir(root.path(), &ws_dir, &state)
+                .await
+                .err()
+                .unwrap();
+            assert!(err.to_string().starts_with("Symlink cycle at"), "{err}");
+            fs::remove_file(ws_dir.join("loop")).unwrap();
+            fs::remove_file(ws_dir.join("net")).unwrap();
+        }
+
+        let outside = tempdir().unwrap();
+        let escape = root
+            .path()
+            .join("envs/../..")
+            .join(outside.path().file_name().unwrap());
+        assert!(escape.starts_with(root.path()));
+        let err = prepare_plan_dir(root.path(), &escape, &state)
+            .await
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("is outside root_dir"), "{err}");
+
+        assert_eq!(
+            config_file(root.path(), &ws_dir),
+            Some(PathBuf::from("envs/prod/main.tf"))
+        );
+        fs::write(
+            ws_dir.join("backend.tf"),
+            "terraform {\n  backend \"s3\" {}\n}\n",
+        )
+        .unwrap();
+        assert_eq!(
+            config_file(root.path(), &ws_dir),
+            Some(PathBuf::from("envs/prod/backend.tf"))
+        );
+        assert_eq!(config_file(root.path(), &root.path().join("modules")), None);
+    }
+}
@@ -0,0 +1,189 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::Result;
+use serde::Deserialize;
+use tokio::sync::{Mutex, OnceCell};
+
+use crate::terraform::{
+    cached_binary, cached_versions, ensure_terraform, install_terraform, terraform_version, Engine,
+};
+
+/// How the version recorded in a state file constrains the binary used to plan it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum VersionPolicy {
+    /// Exactly the state's version.
+    Exact,
+    /// Any patch release of the state's major.minor (highest available wins).
+    #[default]
+    SameMinor,
+    /// The state's version or newer (closest available wins).
+    AtLeast,
+}
+
+/// `major.minor.patch`, ignoring any pre-release/build suffix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct Version(u64, u64, u64);
+
+impl Version {
+    fn parse(s: &str) -> Option<Self> {
+        let core = s.trim().trim_start_matches('v');
+        let core = core.split(['-', '+']).next()?;
+        let mut parts = core.split('.').map(|p| p.parse::<u64>().ok());
+        Some(Version(parts.next()??, parts.next()??, parts.next()??))
+    }
+}
+
+/// Pick the candidate that satisfies `policy` for `required`, preferring the
+/// exact version whatever the policy.
+fn select(
+    policy: VersionPolicy,
+    required: Version,
+    candidates: &[(Version, PathBuf)],
+) -> Option<&PathBuf> {
+    if let Some((_, bin)) = candidates.iter().find(|(v, _)| *v == required) {
+        return Some(bin);
+    }
+    let matching = candidates.iter().filter(|(v, _)| match policy {
+        VersionPolicy::Exact => *v == required,
+        VersionPolicy::SameMinor => (v.0, v.1) == (required.0, required.1),
+        VersionPolicy::AtLeast => *v >= required,
+    });
+    match policy {
+        VersionPolicy::SameMinor => matching.max_by_key(|(v, _)| *v),
+        _ => matching.min_by_key(|(v, _)| *v),
+    }
+    .map(|(_, bin)| bin)
+}
+
+/// Resolves the engine binary for each workspace from the version recorded in
+/// its state, reusing the PATH binary or cached releases where the policy allows
+/// and downloading the state's exact version otherwise.
+pub struct Toolchain {
+    engine: Engine,
+    policy: VersionPolicy,
+    keyring: Option<PathBuf>,
+    /// PATH binary and the version it reports.
+    path_bin: Option<(Version, PathBuf)>,
+    /// Required version -> resolved binary, shared across workspaces.
+    resolved: Mutex<HashMap<String, Arc<OnceCell<PathBuf>>>>,
+}
+
+impl Toolchain {
+    pub async fn new(engine: Engine, policy: VersionPolicy, keyring: Option<&Path>) -> Self {
+        let mut path_bin = None;
+        if let Ok(bin) = which::which(engine.binary_name()) {
+            if let Ok(v) = terraform_version(&bin).await {
+                path_bin = Version::parse(&v).map(|v| (v, bin));
+            }
+        }
+        Toolchain {
+            engine,
+            policy,
+            keyring: keyring.map(Path::to_path_buf),
+            path_bin,
+            resolved: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Binary to plan a state last written by `state_version` with. States
+    /// without a (parseable) version use the default binary.
+    pub async fn binary_for(&self, state_version: Option<&str>) -> Result<PathBuf> {
+        let Some((raw, required)) = state_version.and_then(|s| Some((s, Version::parse(s)?)))
+        else {
+            return ensure_terraform(self.engine, None, self.keyring.as_deref()).await;
+        };
+
+        // Concurrent workspaces needing the same version wait for one download;
+        // other versions resolve in parallel.
+        let cell = self
+            .resolved
+            .lock()
+            .await
+            .entry(raw.to_string())
+            .or_default()
+            .clone();
+        let bin = cell
+            .get_or_try_init(|| async {
+                let mut candidates: Vec<(Version, PathBuf)> = cached_versions(self.engine)
+                    .into_iter()
+                    .filter_map(|v| {
+                        let parsed = Version::parse(&v)?;
+                        Some((parsed, cached_binary(self.engine, &v)?))
+                    })
+                    .collect();
+                candidates.extend(self.path_bin.clone());
+
+                match select(self.policy, required, &candidates) {
+                    Some(bin) => Ok(bin.clone()),
+                    None => install_terraform(self.engine, raw, self.keyring.as_deref()).await,
+                }
+            })
+            .await?;
+        Ok(bin.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidates() -> Vec<(Version, PathBuf)> {
+        ["1.3.2", "1.3.9", "1.5.7", "1.7.5"]
+            .iter()
+            .map(|v| (Version::parse(v).unwrap(), PathBuf::from(v)))
+            .collect()
+    }
+
+    #[test]
+    fn selects_by_policy() {
+        let c = candidates();
+        let req = Version::parse("1.3.4").unwrap();
+        assert_eq!(select(VersionPolicy::Exact, req, &c), None);
+        assert_eq!(
+            select(VersionPolicy::SameMinor, req, &c),
+            Some(&PathBuf::from("1.3.9"))
+        );
+        assert_eq!(
+            select(VersionPolicy::AtLeast, req, &c),
+            Some(&PathBuf::from("1.3.9"))
+        );
+        let req = Version::parse("1.5.7").unwrap();
+        assert_eq!(
+            select(VersionPolicy::Exact, req, &c),
+            Some(&PathBuf::from("1.5.7"))
+        );
+        // An exact match beats a newer patch release.
+        assert_eq!(
+            select(
+                VersionPolicy::SameMinor,
+                Version::parse("1.3.2").unwrap(),
+                &c
+            ),
+            Some(&PathBuf::from("1.3.2"))
+        );
+        // The default no longer plans old states with a newer minor release.
+        assert_eq!(VersionPolicy::default(), VersionPolicy::SameMinor);
+        assert_eq!(
+            select(
+                VersionPolicy::default(),
+                Version::parse("1.4.0").unwrap(),
+                &c
+            ),
+            None
+        );
+        assert_eq!(
+            select(VersionPolicy::AtLeast, Version::parse("1.8.0").unwrap(), &c),
+            None
+        );
+    }
+
+    #[test]
+    fn parses_versions() {
+        assert_eq!(Version::parse("1.6.0-beta1"), Some(Version(1, 6, 0)));
+        assert_eq!(Version::parse("v1.9.3"), Some(Version(1, 9, 3)));
+        assert_eq!(Version::parse("1.9"), None);
+    }
+}
@@ -66,3 +66,671 @@ path = "{}"
         .code(predicate::eq(2))
         .stdout(predicate::str::contains("\"drift\": true"));
 }
+
+#[test]
+fn plans_in_workspace_dir_with_fetched_state() {
+    let state_dir = tempdir().unwrap();
+    fs::write(state_dir.path().join("prod.tfstate"), b"{\"serial\":7}").unwrap();
+
+    // Terraform root with one directory per workspace
+    let root = tempdir().unwrap();
+    fs::create_dir_all(root.path().join("envs/prod")).unwrap();
+    fs::write(root.path().join("envs/prod/main.tf"), b"").unwrap();
+
+    // Stub only reports drift when run inside the workspace copy with the
+    // fetched state wired into the backend override.
+    let bin_dir = tempdir().unwrap();
+    stub_bin(
+        bin_dir.path(),
+        "terraform",
+        r#"#!/usr/bin/env bash
+case "$1" in
+  version) echo '{"terraform_version":"1.7.5"}'; exit 0 ;;
+  init) [[ -f terradrift_override.tf.json ]] || exit 1; exit 0 ;;
+esac
+state=$(sed -n 's/.*"path": "\(.*\)".*/\1/p' terradrift_override.tf.json)
+if [[ -f main.tf ]] && grep -q '"serial":7' "$state"; then
+  echo '{"resource_changes":[{"change":{"actions":["update"]}}]}'
+  exit 2
+fi
+exit 0
+"#,
+    );
+
+    let toml_content = format!(
+        r#"[profiles.prod]
+root_dir = "{}"
+workspace_dir = "envs/{{workspace}}"
+
+[profiles.prod.storage]
+provider = "mock"
+path = "{}"
+"#,
+        root.path().display(),
+        state_dir.path().display()
+    );
+    let toml_file = NamedTempFile::new().unwrap();
+    fs::write(toml_file.path(), toml_content).unwrap();
+
+    let mut cmd = Command::cargo_bin("terradrift").unwrap();
+    cmd.arg("diff")
+        .arg("-p")
+        .arg("prod")
+        .arg("--config")
+        .arg(toml_file.path())
+        .env(
+            "PATH",
+            format!(
+                "{}:{}",
+                bin_dir.path().display(),
+                std::env::var("PATH").unwrap_or_default()
+            ),
+        );
+
+    cmd.assert()
+        .code(predicate::eq(2))
+        .stdout(predicate::str::contains("\"drift\": true"));
+}
+
+#[test]
+fn tofu_engine_reported_in_summary() {
+    let state_dir = tempdir().unwrap();
+    fs::write(state_dir.path().join("ws.tfstate"), b"{}").unwrap();
+
+    // Stub `tofu` binary; a clean plan exits 0.
+    let bin_dir = tempdir().unwrap();
+    stub_bin(
+        bin_dir.path(),
+        "tofu",
+        "#!/usr/bin/env bash\n[[ \"$1\" == version ]] && echo '{\"terraform_version\":\"1.8.0\"}'\nexit 0\n",
+    );
+
+    let toml_content = format!(
+        r#"[profiles.prod]
+engine = "tofu"
+
+[profiles.prod.storage]
+provider = "mock"
+path = "{}"
+"#,
+        state_dir.path().display()
+    );
+    let toml_file = NamedTempFile::new().unwrap();
+    fs::write(toml_file.path(), toml_content).unwrap();
+
+    let mut cmd = Command::cargo_bin("terradrift").unwrap();
+    cmd.arg("diff")
+        .arg("-p")
+        .arg("prod")
+        .arg("--config")
+        .arg(toml_file.path())
+        .env(
+            "PATH",
+            format!(
+                "{}:{}",
+                bin_dir.path().display(),
+                std::env::var("PATH").unwrap_or_default()
+            ),
+        );
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("\"engine\": \"tofu\""));
+}
+
+#[test]
+fn full_scan_reports_exact_counts() {
+    let state_dir = tempdir().unwrap();
+    fs::write(state_dir.path().join("app.tfstate"), b"{}").unwrap();
+
+    // Replays a recorded `terraform plan -json` with four planned changes.
+    let fixture = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/ui/terraform-1.11.4.jsonl"
+    );
+    let bin_dir = tempdir().unwrap();
+    stub_bin(
+        bin_dir.path(),
+        "terraform",
+        &format!(
+            "#!/usr/bin/env bash\n[[ \"$1\" == version ]] && echo '{{\"terraform_version\":\"1.11.4\"}}' && exit 0\ncat '{fixture}'\nexit 2\n"
+        ),
+    );
+
+    let toml_file = NamedTempFile::new().unwrap();
+    fs::write(
+        toml_file.path(),
+        format!(
+            "[profiles.prod.storage]\nprovider = \"mock\"\npath = \"{}\"\n",
+            state_dir.path().display()
+        ),
+    )
+    .unwrap();
+    let path = format!(
+        "{}:{}",
+        bin_dir.path().display(),
+        std::env::var("PATH").unwrap_or_default()
+    );
+
+    let run = |extra: &[&str]| {
+        let mut cmd = Command::cargo_bin("terradrift").unwrap();
+        cmd.args(["diff", "-p", "prod", "--config"])
+            .arg(toml_file.path())
+            .args(extra)
+            .env("PATH", &path);
+        cmd.assert().code(predicate::eq(2))
+    };
+
+    run(&[])
+        .stdout(predicate::str::contains("\"scan_mode\": \"fast\""))
+        .stdout(predicate::str::contains("\"changed_resources\": 1"));
+    run(&["--full-scan"])
+        .stdout(predicate::str::contains("\"scan_mode\": \"full\""))
+        .stdout(predicate::str::contains("\"changed_resources\": 4"))
+        .stdout(predicate::str::contains("\"replace\": 1"))
+        .stdout(predicate::str::contains("\"delete\": 1"));
+}
+
+#[test]
+fn refresh_only_separates_drift_from_pending_changes() {
+    let state_dir = tempdir().unwrap();
+    fs::write(state_dir.path().join("app.tfstate"), b"{}").unwrap();
+
+    // `web` was changed in the console; `queue` only exists in configuration.
+    let web = r#"{"resource":{"addr":"aws_instance.web","module":"","resource_type":"aws_instance","implied_provider":"aws"},"action":"update"}"#;
+    let queue = r#"{"resource":{"addr":"aws_sqs_queue.jobs","module":"","resource_type":"aws_sqs_queue","implied_provider":"aws"},"action":"create"}"#;
+    let bin_dir = tempdir().unwrap();
+    stub_bin(
+        bin_dir.path(),
+        "terraform",
+        &format!(
+            "#!/usr/bin/env bash\n[[ \"$1\" == version ]] && echo '{{\"terraform_version\":\"1.7.5\"}}' && exit 0\n\
+             [[ -n \"$NO_DRIFT\" ]] && [[ \" $* \" == *\" -refresh-only \"* ]] && exit 0\n\
+             [[ -z \"$NO_DRIFT\" ]] && echo '{{\"type\":\"resource_drift\",\"change\":{web}}}'\n\
+             [[ \" $* \" == *\" -refresh-only \"* ]] && exit 2\n\
+             [[ -z \"$NO_DRIFT\" ]] && echo '{{\"type\":\"planned_change\",\"change\":{web}}}'\n\
+             echo '{{\"type\":\"planned_change\",\"change\":{queue}}}'\nexit 2\n"
+        ),
+    );
+
+    let toml_file = NamedTempFile::new().unwrap();
+    fs::write(
+        toml_file.path(),
+        format!(
+            "[profiles.prod]\nmode = \"refresh-only\"\npending_changes = true\n\n[profiles.prod.storage]\nprovider = \"mock\"\npath = \"{}\"\n",
+            state_dir.path().display()
+        ),
+    )
+    .unwrap();
+
+    let run = |no_drift: bool, code: i32| {
+        let mut cmd = Command::cargo_bin("terradrift").unwrap();
+        cmd.args(["diff", "-p", "prod", "--config"])
+            .arg(toml_file.path())
+            .env(
+                "PATH",
+                format!(
+                    "{}:{}",
+                    bin_dir.path().display(),
+                    std::env::var("PATH").unwrap_or_default()
+                ),
+            );
+        if no_drift {
+            cmd.env("NO_DRIFT", "1");
+        }
+        let output = cmd
+            .assert()
+            .code(predicate::eq(code))
+            .get_output()
+            .stdout
+            .clone();
+        let stdout = String::from_utf8(output).unwrap();
+        let json: serde_json::Value =
+            serde_json::from_str(&stdout[stdout.find("{\n").unwrap()..]).unwrap();
+        json["results"][0].clone()
+    };
+
+    let result = run(false, 2);
+    let resources = &result["resources"];
+    assert_eq!(resources[0]["address"], "aws_instance.web");
+    assert_eq!(resources[0]["category"], "infrastructure_drift");
+    assert_eq!(resources.as_array().unwrap().len(), 1);
+    let pending = &result["pending"]["resources"];
+    assert_eq!(pending[0]["address"], "aws_sqs_queue.jobs");
+    assert_eq!(pending[0]["category"], "pending_config_change");
+    assert_eq!(result["changed_resources"], 1);
+    assert_eq!(result["counts"]["update"], 1);
+    assert_eq!(result["counts"]["create"], 0);
+    assert_eq!(result["pending"]["create"], 1);
+
+    // Pending changes alone leave the workspace clean and uncounted.
+    let result = run(true, 0);
+    assert_eq!(result["status"], "clean");
+    assert_eq!(result["changed_resources"], 0);
+    assert_eq!(result["counts"]["create"], 0);
+    assert_eq!(result["pending"]["create"], 1);
+}
+
+#[test]
+fn failed_plan_reports_diagnostics_and_stderr() {
+    let state_dir = tempdir().unwrap();
+    fs::write(state_dir.path().join("app.tfstate"), b"{}").unwrap();
+
+    let bin_dir = tempdir().unwrap();
+    stub_bin(
+        bin_dir.path(),
+        "terraform",
+        "#!/usr/bin/env bash\n[[ \"$1\" == version ]] && echo '{\"terraform_version\":\"1.7.5\"}' && exit 0\n\
+         echo '{\"type\":\"diagnostic\",\"diagnostic\":{\"severity\":\"error\",\"summary\":\"No valid credential sources found\",\"detail\":\"Please see the provider docs.\"}}'\n\
+         echo 'plugin exited: rpc error' >&2\nexit 1\n",
+    );
+
+    let toml_file = NamedTempFile::new().unwrap();
+    fs::write(
+        toml_file.path(),
+        format!(
+            "[profiles.prod.storage]\nprovider = \"mock\"\npath = \"{}\"\n",
+            state_dir.path().display()
+        ),
+    )
+    .unwrap();
+
+    let mut cmd = Command::cargo_bin("terradrift").unwrap();
+    cmd.args(["-v", "diff", "-p", "prod", "--config"])
+        .arg(toml_file.path())
+        .env(
+            "PATH",
+            format!(
+                "{}:{}",
+                bin_dir.path().display(),
+                std::env::var("PATH").unwrap_or_default()
+            ),
+        );
+
+    cmd.assert()
+        .code(predicate::eq(1))
+        .stdout(predicate::str::contains(
+            "terraform plan failed in . (exit status: 1): No valid credential sources found",
+        ))
+        .stdout(predicate::str::contains("\"severity\": \"error\""))
+        .stdout(predicate::str::contains(
+            "\"stderr\": \"plugin exited: rpc error\"",
+        ))
+        .stderr(predicate::str::contains(
+            "[app] error: No valid credential sources found",
+        ))
+        .stderr(predicate::str::contains(
+            "    Please see the provider docs.",
+        ));
+}
+
+#[test]
+fn writes_each_format_to_its_output() {
+    let state_dir = tempdir().unwrap();
+    fs::write(state_dir.path().join("app.tfstate"), b"{}").unwrap();
+
+    let bin_dir = tempdir().unwrap();
+    stub_bin(
+        bin_dir.path(),
+        "terraform",
+        "#!/usr/bin/env bash\n[[ \"$1\" == version ]] && echo '{\"terraform_version\":\"1.7.5\"}' && exit 0\necho '{\"resource_changes\":[{\"address\":\"aws_instance.web\",\"change\":{\"actions\":[\"update\"]}}]}'\nexit 2\n",
+    );
+
+    let toml_file = NamedTempFile::new().unwrap();
+    fs::write(
+        toml_file.path(),
+        format!(
+            "[profiles.prod.storage]\nprovider = \"mock\"\npath = \"{}\"\n",
+            state_dir.path().display()
+        ),
+    )
+    .unwrap();
+    let out_dir = tempdir().unwrap();
+    let csv = out_dir.path().join("drift.csv");
+
+    let mut cmd = Command::cargo_bin("terradrift").unwrap();
+    cmd.args(["diff", "-p", "prod", "--config"])
+        .arg(toml_file.path())
+        .args(["--format", "csv", "--output"])
+        .arg(&csv)
+        .args(["--format", "json"])
+        .env(
+            "PATH",
+            format!(
+                "{}:{}",
+                bin_dir.path().display(),
+                std::env::var("PATH").unwrap_or_default()
+            ),
+        );
+
+    // stdout carries nothing but the JSON document, so it pipes into jq.
+    let stdout = cmd
+        .assert()
+        .code(predicate::eq(2))
+        .get_output()
+        .stdout
+        .clone();
+    let json: serde_json::Value = serde_json::from_slice(&stdout).unwrap();
+    assert_eq!(
+        json["results"][0]["resources"][0]["address"],
+        "aws_instance.web"
+    );
+
+    let csv = fs::read_to_string(csv).unwrap();
+    assert!(csv.starts_with("profile,workspace,status,"));
+    assert!(csv.contains("\nprod,app,drift,terraform,1.7.5,true,fast,1,0,1,0,0,"));
+}
+
+fn stub_bin(dir: &std::path::Path, name: &str, script: &str) {
+    let path = dir.join(name);
+    fs::write(&path, script).unwrap();
+    let mut perms = fs::metadata(&path).unwrap().permissions();
+    perms.set_mode(0o755);
+    fs::set_permissions(&path, perms).unwrap();
+}
+
+/// Whether `pid` is still running a few seconds on. A killed process can
+/// linger as a zombie until it is reaped, so that counts as gone.
+fn still_running(pid: &str) -> bool {
+    for _ in 0..50 {
+        let out = std::process::Command::new("ps")
+            .args(["-o", "stat=", "-p", pid.trim()])
+            .output()
+            .unwrap();
+        let stat = String::from_utf8_lossy(&out.stdout);
+        if !out.status.success() || stat.trim_start().starts_with('Z') {
+            return false;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(100));
+    }
+    true
+}
+
+#[test]
+fn picks_cached_binary_matching_state_version() {
+    let state_dir = tempdir().unwrap();
+    fs::write(
+        state_dir.path().join("legacy.tfstate"),
+        br#"{"version":4,"terraform_version":"1.3.2"}"#,
+    )
+    .unwrap();
+
+    // PATH has 1.7.5; the cache has a verified 1.3.9 next to its digest record.
+    let bin_dir = tempdir().unwrap();
+    stub_bin(
+        bin_dir.path(),
+        "terraform",
+        "#!/usr/bin/env bash\n[[ \"$1\" == version ]] && echo '{\"terraform_version\":\"1.7.5\"}'\nexit 0\n",
+    );
+    let cache = tempdir().unwrap();
+    let cached_dir = cache.path().join("1.3.9");
+    fs::create_dir_all(&cached_dir).unwrap();
+    stub_bin(
+        &cached_dir,
+        "terraform",
+        "#!/usr/bin/env bash\n[[ \"$1\" == version ]] && echo '{\"terraform_version\":\"1.3.9\"}'\nexit 0\n",
+    );
+    fs::write(
+        cached_dir.join("terraform.sha256"),
+        "00  terraform_1.3.9_linux_amd64.zip\n",
+    )
+    .unwrap();
+
+    let toml_content = format!(
+        r#"[profiles.prod]
+version_policy = "same-minor"
+
+[profiles.prod.storage]
+provider = "mock"
+path = "{}"
+"#,
+        state_dir.path().display()
+    );
+    let toml_file = NamedTempFile::new().unwrap();
+    fs::write(toml_file.path(), toml_content).unwrap();
+
+    let mut cmd = Command::cargo_bin("terradrift").unwrap();
+    cmd.arg("diff")
+        .arg("-p")
+        .arg("prod")
+        .arg("--config")
+        .arg(toml_file.path())
+        .env(
+            "PATH",
+            format!(
+                "{}:{}",
+                bin_dir.path().display(),
+                std::env::var("PATH").unwrap_or_default()
+            ),
+        )
+        .env("TERRADRIFT_TF_CACHE", cache.path());
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("\"terraform_version\": \"1.3.9\""));
+}
+
+#[test]
+fn hung_plan_times_out_and_kills_process_tree() {
+    let state_dir = tempdir().unwrap();
+    fs::write(state_dir.path().join("hung.tfstate"), b"{}").unwrap();
+
+    // Plan spawns a long-lived "provider plugin" and waits on it forever.
+    let bin_dir = tempdir().unwrap();
+    let pid_file = bin_dir.path().join("plugin.pid");
+    stub_bin(
+        bin_dir.path(),
+        "terraform",
+        &format!(
+            "#!/usr/bin/env bash\n[[ \"$1\" == version ]] && echo '{{\"terraform_version\":\"1.7.5\"}}' && exit 0\nsleep 60 &\necho $! > {}\nwait\n",
+            pid_file.display()
+        ),
+    );
+
+    let toml_content = format!(
+        r#"[profiles.prod]
+timeout = "1h"
+
+[profiles.prod.storage]
+provider = "mock"
+path = "{}"
+"#,
+        state_dir.path().display()
+    );
+    let toml_file = NamedTempFile::new().unwrap();
+    fs::write(toml_file.path(), toml_content).unwrap();
+
+    let mut cmd = Command::cargo_bin("terradrift").unwrap();
+    cmd.arg("diff")
+        .arg("-p")
+        .arg("prod")
+        .arg("--timeout")
+        .arg("1s")
+        .arg("--config")
+        .arg(toml_file.path())
+        .env(
+            "PATH",
+            format!(
+                "{}:{}",
+                bin_dir.path().display(),
+                std::env::var("PATH").unwrap_or_default()
+            ),
+        );
+
+    cmd.assert()
+        .code(predicate::eq(1))
+        .stdout(predicate::str::contains("\"status\": \"timeout\""));
+
+    let pid = fs::read_to_string(&pid_file).unwrap();
+    assert!(
+        !still_running(&pid),
+        "plugin process outlived the timed-out plan"
+    );
+}
+
+#[test]
+fn hung_init_times_out_and_kills_process_tree() {
+    let state_dir = tempdir().unwrap();
+    fs::write(state_dir.path().join("hung.tfstate"), b"{}").unwrap();
+    let root = tempdir().unwrap();
+    fs::create_dir_all(root.path().join("envs/hung")).unwrap();
+
+    // Init starts a provider download that never finishes; plan is never reached.
+    let bin_dir = tempdir().unwrap();
+    let pid_file = bin_dir.path().join("download.pid");
+    stub_bin(
+        bin_dir.path(),
+        "terraform",
+        &format!(
+            "#!/usr/bin/env bash\n[[ \"$1\" == version ]] && echo '{{\"terraform_version\":\"1.7.5\"}}' && exit 0\n[[ \"$1\" == init ]] || exit 0\nsleep 60 &\necho $! > {}\nwait\n",
+            pid_file.display()
+        ),
+    );
+
+    let toml_content = format!(
+        r#"[profiles.prod]
+root_dir = "{}"
+workspace_dir = "envs/{{workspace}}"
+timeout = "1s"
+
+[profiles.prod.storage]
+provider = "mock"
+path = "{}"
+"#,
+        root.path().display(),
+        state_dir.path().display()
+    );
+    let toml_file = NamedTempFile::new().unwrap();
+    fs::write(toml_file.path(), toml_content).unwrap();
+
+    let mut cmd = Command::cargo_bin("terradrift").unwrap();
+    cmd.arg("diff")
+        .arg("-p")
+        .arg("prod")
+        .arg("--config")
+        .arg(toml_file.path())
+        .env(
+            "PATH",
+            format!(
+                "{}:{}",
+                bin_dir.path().display(),
+                std::env::var("PATH").unwrap_or_default()
+            ),
+        );
+
+    cmd.assert()
+        .code(predicate::eq(1))
+        .stdout(predicate::str::contains("\"status\": \"timeout\""))
+        .stdout(predicate::str::contains("terraform init timed out"));
+
+    let pid = fs::read_to_string(&pid_file).unwrap();
+    assert!(
+        !still_running(&pid),
+        "download process outlived the timed-out init"
+    );
+}
+
+#[test]
+fn failing_workspace_does_not_hide_drift() {
+    let state_dir = tempdir().unwrap();
+    fs::write(state_dir.path().join("drifty.tfstate"), b"{}").unwrap();
+    fs::write(state_dir.path().join("orphan.tfstate"), b"{}").unwrap();
+
+    // `orphan` has no configuration directory, so its scan fails.
+    let root = tempdir().unwrap();
+    fs::create_dir_all(root.path().join("envs/drifty")).unwrap();
+
+    let bin_dir = tempdir().unwrap();
+    stub_bin(
+        bin_dir.path(),
+        "terraform",
+        "#!/usr/bin/env bash\n[[ \"$1\" == version ]] && echo '{\"terraform_version\":\"1.7.5\"}' && exit 0\n[[ \"$1\" == init ]] && exit 0\necho '{\"resource_changes\":[{\"change\":{\"actions\":[\"delete\"]}}]}'\nexit 2\n",
+    );
+
+    let toml_content = format!(
+        r#"[profiles.prod]
+root_dir = "{}"
+workspace_dir = "envs/{{workspace}}"
+
+[profiles.prod.storage]
+provider = "mock"
+path = "{}"
+"#,
+        root.path().display(),
+        state_dir.path().display()
+    );
+    let toml_file = NamedTempFile::new().unwrap();
+    fs::write(toml_file.path(), toml_content).unwrap();
+
+    let mut cmd = Command::cargo_bin("terradrift").unwrap();
+    cmd.arg("diff")
+        .arg("-p")
+        .arg("prod")
+        .arg("--config")
+        .arg(toml_file.path())
+        .env(
+            "PATH",
+            format!(
+                "{}:{}",
+                bin_dir.path().display(),
+                std::env::var("PATH").unwrap_or_default()
+            ),
+        );
+
+    cmd.assert()
+        .code(predicate::eq(3))
+        .stdout(predicate::str::contains("\"status\": \"drift\""))
+        .stdout(predicate::str::contains("\"status\": \"error\""))
+        .stdout(predicate::str::contains(
+            "Terraform configuration directory not found",
+        ));
+}
+
+#[test]
+fn unwritable_actions_summary_keeps_drift_exit_code() {
+    let state_dir = tempdir().unwrap();
+    fs::write(state_dir.path().join("drifty.tfstate"), b"{}").unwrap();
+
+    let bin_dir = tempdir().unwrap();
+    stub_bin(
+        bin_dir.path(),
+        "terraform",
+        "#!/usr/bin/env bash\n[[ \"$1\" == version ]] && echo '{\"terraform_version\":\"1.7.5\"}' && exit 0\necho '{\"resource_changes\":[{\"change\":{\"actions\":[\"update\"]}}]}'\nexit 2\n",
+    );
+
+    let toml_content = format!(
+        r#"[profiles.prod.storage]
+provider = "mock"
+path = "{}"
+"#,
+        state_dir.path().display()
+    );
+    let toml_file = NamedTempFile::new().unwrap();
+    fs::write(toml_file.path(), toml_content).unwrap();
+
+    // A directory cannot be appended to as the job summary.
+    let summary_dir = tempdir().unwrap();
+    let mut cmd = Command::cargo_bin("terradrift").unwrap();
+    cmd.arg("diff")
+        .arg("-p")
+        .arg("prod")
+        .arg("--config")
+        .arg(toml_file.path())
+        .env("GITHUB_ACTIONS", "true")
+        .env("GITHUB_STEP_SUMMARY", summary_dir.path())
+        .env_remove("GITHUB_OUTPUT")
+        .env(
+            "PATH",
+            format!(
+                "{}:{}",
+                bin_dir.path().display(),
+                std::env::var("PATH").unwrap_or_default()
+            ),
+        );
+
+    cmd.assert()
+        .code(predicate::eq(2))
+        .stderr(predicate::str::contains(
+            "warning: reporting to GitHub Actions",
+        ));
+}
@@ -46,6 +46,8 @@ if [[ $EXIT -eq 0 ]]; then
   echo "✅ No drift detected"
 elif [[ $EXIT -eq 2 ]]; then
   echo "🚨 Drift detected! (exit 2)"
+elif [[ $EXIT -eq 3 ]]; then
+  echo "🚨 Drift detected, some workspaces failed (exit 3)" >&2
 else
   echo "❌ Terradrift failed with exit code $EXIT" >&2
 fi